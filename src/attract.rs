@@ -0,0 +1,149 @@
+// Attract mode: what the cabinet does while nobody is playing.
+//
+// Title -> character/nickname introduction -> demo game -> Title ...
+// Any key press drops back to the title screen.
+//
+// The demo is a regular `Game` driven by a recorded input sequence. The
+// simulation is deterministic so it plays out the same way every time.
+use crate::config::Config;
+use crate::game::{Game, Phase, TICKS_PER_SECOND};
+use crate::input::GameInput;
+
+const TITLE_TICKS: u32 = 5 * TICKS_PER_SECOND;
+
+// each ghost's row of the introduction is revealed in three steps
+pub const INTRO_TICKS_PER_GHOST: u32 = 90;
+const INTRO_CHARACTER_DELAY: u32 = 30;
+const INTRO_NICKNAME_DELAY: u32 = 60;
+const INTRO_TICKS: u32 = 4 * INTRO_TICKS_PER_GHOST + 2 * TICKS_PER_SECOND;
+
+const DEMO_SEED: u64 = 0x0D3E_0000;
+
+// (input held, for how many ticks)
+const DEMO_INPUT: &[(GameInput, u32)] = &[
+    (GameInput::Nil, 120),
+    (GameInput::Left, 60),
+    (GameInput::Up, 40),
+    (GameInput::Right, 60),
+    (GameInput::Up, 50),
+    (GameInput::Left, 50),
+    (GameInput::Up, 60),
+    (GameInput::Right, 90),
+    (GameInput::Down, 60),
+    (GameInput::Right, 60),
+    (GameInput::Down, 90),
+    (GameInput::Left, 60),
+    (GameInput::Down, 60),
+    (GameInput::Right, 90),
+    (GameInput::Up, 60),
+    (GameInput::Left, 120),
+    (GameInput::Down, 90),
+    (GameInput::Left, 120),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttractStage {
+    Title,
+    Intro,
+    Demo,
+}
+
+// How much of a ghost's row of the introduction screen is showing
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum IntroReveal {
+    Hidden,
+    Picture,
+    Character,
+    Nickname,
+}
+
+pub struct Attract {
+    pub stage: AttractStage,
+    // ticks spent in the current stage
+    pub ticks: u32,
+    pub demo: Option<Game>,
+    config: Config,
+}
+
+impl Attract {
+    pub fn new(config: Config) -> Attract {
+        Attract {
+            stage: AttractStage::Title,
+            ticks: 0,
+            demo: None,
+            config,
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.ticks += 1;
+        match self.stage {
+            AttractStage::Title => {
+                if self.ticks >= TITLE_TICKS {
+                    self.enter(AttractStage::Intro);
+                }
+            }
+            AttractStage::Intro => {
+                if self.ticks >= INTRO_TICKS {
+                    self.enter(AttractStage::Demo);
+                }
+            }
+            AttractStage::Demo => {
+                let input = demo_input(self.ticks - 1);
+                let demo = self.demo.as_mut().unwrap();
+                demo.tick(input);
+                // the demo ends with Pac-Man's first death, or when the recording runs out
+                let died = matches!(demo.phase, Phase::Dying(_) | Phase::GameOver);
+                if died || input_is_exhausted(self.ticks) {
+                    self.enter(AttractStage::Title);
+                }
+            }
+        }
+    }
+
+    pub fn on_key(&mut self) {
+        self.enter(AttractStage::Title);
+    }
+
+    pub fn intro_reveal(&self, ghost: usize) -> IntroReveal {
+        if self.stage != AttractStage::Intro {
+            return IntroReveal::Hidden;
+        }
+        let start = ghost as u32 * INTRO_TICKS_PER_GHOST;
+        match self.ticks.checked_sub(start) {
+            None => IntroReveal::Hidden,
+            Some(t) if t < INTRO_CHARACTER_DELAY => IntroReveal::Picture,
+            Some(t) if t < INTRO_NICKNAME_DELAY => IntroReveal::Character,
+            Some(_) => IntroReveal::Nickname,
+        }
+    }
+
+    fn enter(&mut self, stage: AttractStage) {
+        self.stage = stage;
+        self.ticks = 0;
+        self.demo = match stage {
+            AttractStage::Demo => {
+                let mut config = self.config;
+                config.lives = 1;
+                config.start_level = 1;
+                Some(Game::new(config, 1, DEMO_SEED))
+            }
+            _ => None,
+        };
+    }
+}
+
+fn demo_input(tick: u32) -> GameInput {
+    let mut start = 0;
+    for &(input, len) in DEMO_INPUT {
+        if tick < start + len {
+            return input;
+        }
+        start += len;
+    }
+    GameInput::Nil
+}
+
+fn input_is_exhausted(tick: u32) -> bool {
+    tick >= DEMO_INPUT.iter().map(|&(_, len)| len).sum::<u32>()
+}
@@ -0,0 +1,221 @@
+// Lots of seeded games played at once, spread over threads, for judging a bot
+// or a ghost AI on more than a handful of games.
+//
+// Threads take the next seed nobody has played yet and results are put back
+// in seed order, so the same seeds always give the same report however many
+// threads there are.
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::game::{Game, GhostKind, TICKS_PER_SECOND};
+use crate::headless::{HeadlessRunner, InputSource, RunSummary};
+use crate::stats::GameStats;
+
+// an hour of play, far longer than any game lasts
+pub const DEFAULT_MAX_TICKS: u64 = 60 * 60 * TICKS_PER_SECOND as u64;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Batch {
+    pub seeds: Range<u64>,
+    pub threads: usize,
+    // games still going after this many ticks are stopped
+    pub max_ticks: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameResult {
+    pub seed: u64,
+    pub summary: RunSummary,
+    pub stats: GameStats,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatchReport {
+    // in seed order
+    pub games: Vec<GameResult>,
+}
+
+impl Batch {
+    pub fn new(seeds: Range<u64>, threads: usize) -> Batch {
+        Batch {
+            seeds,
+            threads,
+            max_ticks: DEFAULT_MAX_TICKS,
+        }
+    }
+
+    // Play every seed. `new_game` sets up the game for a seed (rules, maze,
+    // ghost AIs) and `new_input` makes a fresh player for each game.
+    pub fn run<I: InputSource>(
+        &self,
+        new_game: impl Fn(u64) -> Game + Sync,
+        new_input: impl Fn() -> I + Sync,
+    ) -> BatchReport {
+        let next_seed = AtomicU64::new(self.seeds.start);
+        let results = Mutex::new(Vec::new());
+        std::thread::scope(|scope| {
+            for _ in 0..self.threads.max(1) {
+                scope.spawn(|| loop {
+                    // never past the end, which may be u64::MAX
+                    let end = self.seeds.end;
+                    let claimed =
+                        next_seed.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |seed| {
+                            if seed < end {
+                                Some(seed + 1)
+                            } else {
+                                None
+                            }
+                        });
+                    let seed = match claimed {
+                        Ok(seed) => seed,
+                        Err(_) => break,
+                    };
+                    let result = play(seed, new_game(seed), &mut new_input(), self.max_ticks);
+                    results.lock().unwrap().push(result);
+                });
+            }
+        });
+
+        let mut games = results.into_inner().unwrap();
+        games.sort_by_key(|g| g.seed);
+        BatchReport { games }
+    }
+}
+
+fn play(seed: u64, game: Game, input: &mut dyn InputSource, max_ticks: u64) -> GameResult {
+    let mut stats = GameStats::new(seed, &game);
+    let summary = HeadlessRunner::from_game(game).run_until(max_ticks, input, |game| {
+        stats.observe(game);
+        false
+    });
+    GameResult {
+        seed,
+        summary,
+        stats,
+    }
+}
+
+impl BatchReport {
+    pub fn average_score(&self) -> f64 {
+        if self.games.is_empty() {
+            return 0.0;
+        }
+        let total: u64 = self.games.iter().map(|g| g.summary.score as u64).sum();
+        total as f64 / self.games.len() as f64
+    }
+
+    pub fn best(&self) -> Option<&GameResult> {
+        self.games
+            .iter()
+            .max_by_key(|g| (g.summary.score, std::cmp::Reverse(g.seed)))
+    }
+
+    pub fn worst(&self) -> Option<&GameResult> {
+        self.games.iter().min_by_key(|g| (g.summary.score, g.seed))
+    }
+
+    // level reached -> number of games
+    pub fn levels_reached(&self) -> BTreeMap<u32, usize> {
+        let mut levels = BTreeMap::new();
+        for game in self.games.iter() {
+            *levels.entry(game.summary.level).or_insert(0) += 1;
+        }
+        levels
+    }
+
+    // lives lost to each ghost, indexed by `GhostKind::index`
+    pub fn deaths_by_ghost(&self) -> [usize; 4] {
+        let mut deaths = [0; 4];
+        for death in self.games.iter().flat_map(|g| g.stats.deaths.iter()) {
+            deaths[death.ghost.index()] += 1;
+        }
+        deaths
+    }
+
+    // games stopped by `Batch::max_ticks` before the game was over
+    pub fn unfinished(&self) -> usize {
+        self.games.iter().filter(|g| !g.summary.game_over).count()
+    }
+}
+
+impl fmt::Display for BatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (best, worst) = match (self.best(), self.worst()) {
+            (Some(best), Some(worst)) => (best, worst),
+            _ => return writeln!(f, "no games played"),
+        };
+        writeln!(f, "{} games", self.games.len())?;
+        writeln!(
+            f,
+            "score: average {:.1}, best {} (seed {}), worst {} (seed {})",
+            self.average_score(),
+            best.summary.score,
+            best.seed,
+            worst.summary.score,
+            worst.seed
+        )?;
+
+        writeln!(f, "level reached:")?;
+        for (level, count) in self.levels_reached() {
+            writeln!(
+                f,
+                "  {:>3}  {:>6} games  {:>5.1}%",
+                level,
+                count,
+                100.0 * count as f64 / self.games.len() as f64
+            )?;
+        }
+
+        let deaths = self.deaths_by_ghost();
+        let total: usize = deaths.iter().sum();
+        writeln!(f, "lives lost: {}", total)?;
+        for kind in GhostKind::ALL.iter() {
+            let count = deaths[kind.index()];
+            let share = if total > 0 {
+                100.0 * count as f64 / total as f64
+            } else {
+                0.0
+            };
+            writeln!(
+                f,
+                "  {:<8}{:>6}  {:>5.1}%",
+                format!("{:?}", kind),
+                count,
+                share
+            )?;
+        }
+
+        let unfinished = self.unfinished();
+        if unfinished > 0 {
+            writeln!(
+                f,
+                "{} games hit the tick limit and were stopped",
+                unfinished
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Batch;
+    use crate::config::Config;
+    use crate::game::Game;
+    use crate::input::GameInput;
+
+    #[test]
+    fn seeds_up_to_u64_max() {
+        let mut batch = Batch::new(u64::MAX - 5..u64::MAX, 3);
+        batch.max_ticks = 10;
+        let report = batch.run(
+            |seed| Game::new(Config::default(), 1, seed),
+            || |_: &Game| GameInput::Nil,
+        );
+        let seeds: Vec<u64> = report.games.iter().map(|g| g.seed).collect();
+        assert_eq!(seeds, (u64::MAX - 5..u64::MAX).collect::<Vec<u64>>());
+    }
+}
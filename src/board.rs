@@ -1,8 +1,9 @@
-use crate::vec2::Vec2;
+use crate::vec2::{Fixed, Vec2};
 // X = wall
 // . = pellet
 // o = power pellet
 // t = tunnel
+// - = ghost house door
 const MAZE_DEF: &str = "\
                     XXXXXXXXXXXXXXXXXXXXXXXXXXXX\
                     X............XX............X\
@@ -37,84 +38,183 @@ const MAZE_DEF: &str = "\
                     XXXXXXXXXXXXXXXXXXXXXXXXXXXX\
                     ";
 
+#[derive(Clone)]
 pub struct Board {
     pub is_traversable: Vec<bool>,
     pub is_tunnel: Vec<bool>,
     pub has_pellet: Vec<bool>,
     pub has_power_pellet: Vec<bool>,
+    pub is_ghost_door: Vec<bool>,
+
+    pub num_pellets_at_start: usize,
 
     pub width: usize,
     pub height: usize,
     pub num_tiles: usize,
 }
 
-
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct BoardPos {
     pub x: usize,
     pub y: usize,
 }
 
+// Declared in the order the arcade breaks ties when two directions are equally good
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dir {
+    Up,
+    Left,
+    Down,
+    Right,
+}
+
+impl Dir {
+    pub const ALL: [Dir; 4] = [Dir::Up, Dir::Left, Dir::Down, Dir::Right];
+
+    pub fn opposite(self) -> Dir {
+        match self {
+            Dir::Up => Dir::Down,
+            Dir::Left => Dir::Right,
+            Dir::Down => Dir::Up,
+            Dir::Right => Dir::Left,
+        }
+    }
 
+    // in tiles, +y is down the screen
+    pub fn offset(self) -> (i32, i32) {
+        match self {
+            Dir::Up => (0, -1),
+            Dir::Left => (-1, 0),
+            Dir::Down => (0, 1),
+            Dir::Right => (1, 0),
+        }
+    }
+}
+
+// The tile whose top-left corner is at or above and left of `p`
 impl From<Vec2> for BoardPos {
     fn from(p: Vec2) -> BoardPos {
+        let tile = Fixed::pixels(Board::TILE_WIDTH);
         BoardPos {
-            x: (p.x / Board::TILE_WIDTH) as usize,
-            y: (p.y / Board::TILE_WIDTH) as usize,
+            x: p.x.div_euclid(tile).max(0) as usize,
+            y: p.y.div_euclid(tile).max(0) as usize,
         }
     }
 }
 
+impl Default for Board {
+    fn default() -> Board {
+        Board::new()
+    }
+}
 
 impl Board {
-    pub const TILE_WIDTH: f32 = 8.0;
+    // in pixels
+    pub const TILE_WIDTH: i32 = 8;
+    pub const WIDTH: usize = 28;
+    pub const HEIGHT: usize = 31;
+
     pub fn new() -> Board {
-        let width = 28;
-        let height = 31;
+        Board::from_tile_chars(MAZE_DEF.chars())
+    }
+
+    // A maze file has one line per row, using the same characters as MAZE_DEF.
+    // The game's start positions are fixed so it has to be the arcade's size.
+    pub fn from_maze_str(contents: &str) -> Result<Board, String> {
+        let mut rows: Vec<&str> = contents.lines().map(|l| l.trim_end_matches('\r')).collect();
+        while rows.last() == Some(&"") {
+            rows.pop();
+        }
+        if rows.len() != Board::HEIGHT {
+            return Err(format!(
+                "expected {} rows, got {}",
+                Board::HEIGHT,
+                rows.len()
+            ));
+        }
+        for (y, row) in rows.iter().enumerate() {
+            if row.chars().count() != Board::WIDTH {
+                return Err(format!("row {}: expected {} tiles", y + 1, Board::WIDTH));
+            }
+            if let Some(c) = row.chars().find(|c| !"X.ot- ".contains(*c)) {
+                return Err(format!("row {}: unknown tile `{}`", y + 1, c));
+            }
+        }
+        let board = Board::from_tile_chars(rows.iter().flat_map(|row| row.chars()));
+        if board.num_pellets_at_start == 0 {
+            return Err("the maze has no pellets".to_string());
+        }
+        Ok(board)
+    }
+
+    pub fn load(path: &std::path::Path) -> Result<Board, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+        Board::from_maze_str(&contents).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    fn from_tile_chars(tiles: impl Iterator<Item = char>) -> Board {
+        let width = Board::WIDTH;
+        let height = Board::HEIGHT;
         let num_tiles = width * height;
 
         let mut is_traversable = Vec::with_capacity(num_tiles);
         let mut has_power_pellet = Vec::with_capacity(num_tiles);
         let mut has_pellet = Vec::with_capacity(num_tiles);
         let mut is_tunnel = Vec::with_capacity(num_tiles);
+        let mut is_ghost_door = Vec::with_capacity(num_tiles);
 
-        for c in MAZE_DEF.chars() {
+        for c in tiles {
             is_traversable.push(c != 'X');
             has_power_pellet.push(c == 'o');
             has_pellet.push(c == '.');
             is_tunnel.push(c == 't');
+            is_ghost_door.push(c == '-');
         }
 
         assert_eq!(num_tiles, is_traversable.len());
         assert_eq!(num_tiles, is_tunnel.len());
         assert_eq!(num_tiles, has_pellet.len());
         assert_eq!(num_tiles, has_power_pellet.len());
+        assert_eq!(num_tiles, is_ghost_door.len());
+
+        let num_pellets_at_start = has_pellet
+            .iter()
+            .chain(has_power_pellet.iter())
+            .filter(|&&p| p)
+            .count();
 
         Board {
             is_traversable,
             is_tunnel,
             has_pellet,
             has_power_pellet,
+            is_ghost_door,
+            num_pellets_at_start,
             width,
             height,
             num_tiles,
         }
     }
 
-    pub fn get_local_pos_of_tile(&self, h: usize) ->  Vec2 {
-        Vec2 {
-            x: (h % self.width) as f32 * Board::TILE_WIDTH,
-            y: (h / self.width) as f32 * Board::TILE_WIDTH,
-        }
-
+    pub fn get_local_pos_of_tile(&self, h: usize) -> Vec2 {
+        Vec2::from_pixels(
+            (h % self.width) as i32 * Board::TILE_WIDTH,
+            (h / self.width) as i32 * Board::TILE_WIDTH,
+        )
     }
-    pub fn get_board_pos_of_tile(&self, h: usize) ->  BoardPos {
+    pub fn get_board_pos_of_tile(&self, h: usize) -> BoardPos {
         assert!(h < self.num_tiles);
         BoardPos {
             x: h % self.width,
             y: h / self.width,
         }
     }
-    pub fn get_tile_of_board_pos(&self, board_pos: BoardPos) ->  usize {
+    pub fn contains(&self, board_pos: BoardPos) -> bool {
+        board_pos.x < self.width && board_pos.y < self.height
+    }
+
+    pub fn get_tile_of_board_pos(&self, board_pos: BoardPos) -> usize {
         assert!(board_pos.x < self.width);
         assert!(board_pos.y < self.height);
 
@@ -140,4 +240,56 @@ impl Board {
         assert!(h < self.has_power_pellet.len());
         self.has_power_pellet[h]
     }
+
+    pub fn tile_is_ghost_door(&self, h: usize) -> bool {
+        assert!(h < self.is_ghost_door.len());
+        self.is_ghost_door[h]
+    }
+
+    // The board as it is now, in the maze file format
+    pub fn rows(&self) -> Vec<String> {
+        (0..self.height)
+            .map(|y| {
+                (0..self.width)
+                    .map(|x| {
+                        let h = y * self.width + x;
+                        if !self.is_traversable[h] {
+                            'X'
+                        } else if self.has_pellet[h] {
+                            '.'
+                        } else if self.has_power_pellet[h] {
+                            'o'
+                        } else if self.is_tunnel[h] {
+                            't'
+                        } else if self.is_ghost_door[h] {
+                            '-'
+                        } else {
+                            ' '
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // pellets and power pellets
+    pub fn num_pellets_remaining(&self) -> usize {
+        self.has_pellet
+            .iter()
+            .chain(self.has_power_pellet.iter())
+            .filter(|&&p| p)
+            .count()
+    }
+
+    // The tile next to `board_pos`, wrapping left/right through the tunnel.
+    // None when stepping off the top or bottom of the board.
+    pub fn neighbor(&self, board_pos: BoardPos, dir: Dir) -> Option<BoardPos> {
+        let (dx, dy) = dir.offset();
+        let x = (board_pos.x as i32 + dx).rem_euclid(self.width as i32) as usize;
+        let y = board_pos.y as i32 + dy;
+        if y < 0 || y >= self.height as i32 {
+            return None;
+        }
+        Some(BoardPos { x, y: y as usize })
+    }
 }
@@ -0,0 +1,206 @@
+// Autopilot: a bot at the joystick.
+//
+// Every tick it does a breadth first search from Pac-Man to the nearest thing
+// worth eating, steering around tiles a dangerous ghost could reach soon.
+// Frightened ghosts are food while there's time left to catch them. When
+// every way is blocked it heads for whichever neighbouring tile is furthest
+// from the ghosts. It's not clever, but it's deterministic, so games it plays
+// are as reproducible as any other.
+use std::collections::VecDeque;
+
+use crate::board::{Board, BoardPos, Dir};
+use crate::game::{self, Game, GhostState, Phase, TICKS_PER_SECOND};
+use crate::headless::InputSource;
+use crate::input::GameInput;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Autopilot {
+    // tiles a ghost can reach within this many steps are avoided
+    pub danger_radius: u32,
+    // frightened ghosts are chased while there's at least this long left
+    pub chase_fright_ticks: u32,
+}
+
+impl Default for Autopilot {
+    fn default() -> Autopilot {
+        Autopilot {
+            danger_radius: 4,
+            chase_fright_ticks: 2 * TICKS_PER_SECOND,
+        }
+    }
+}
+
+impl Autopilot {
+    pub fn new() -> Autopilot {
+        Autopilot::default()
+    }
+
+    pub fn choose_input(&self, game: &Game) -> GameInput {
+        if game.phase != Phase::Playing {
+            return GameInput::Nil;
+        }
+        let board = game.board();
+        let start = game.pacman_tile();
+
+        // how far each tile is from the nearest ghost that can kill Pac-Man
+        let dangerous: Vec<BoardPos> = game
+            .ghosts
+            .iter()
+            .filter(|g| !g.frightened)
+            .filter(|g| g.state == GhostState::Active || g.state == GhostState::LeavingHouse)
+            .map(|g| game.ghost_tile(g.kind))
+            .collect();
+        let ghost_dist = distances(board, &dangerous);
+
+        let chase_ghosts = game.fright_ticks >= self.chase_fright_ticks;
+        let frightened: Vec<BoardPos> = game
+            .ghosts
+            .iter()
+            .filter(|g| g.frightened && g.state == GhostState::Active)
+            .map(|g| game.ghost_tile(g.kind))
+            .collect();
+        let is_food = |tile: BoardPos| {
+            let h = board.get_tile_of_board_pos(tile);
+            board.tile_has_pellet(h)
+                || board.tile_has_power_pellet(h)
+                || (chase_ghosts && frightened.contains(&tile))
+                || (game.bonus_fruit.is_some() && tile == game::FRUIT_POS)
+        };
+        let safe = |tile: BoardPos| {
+            let h = board.get_tile_of_board_pos(tile);
+            ghost_dist[h] > self.danger_radius
+        };
+
+        let dir = first_step_to(board, start, is_food, safe)
+            .or_else(|| escape(board, start, &ghost_dist));
+        match dir {
+            Some(Dir::Up) => GameInput::Up,
+            Some(Dir::Left) => GameInput::Left,
+            Some(Dir::Down) => GameInput::Down,
+            Some(Dir::Right) => GameInput::Right,
+            None => GameInput::Nil,
+        }
+    }
+}
+
+impl InputSource for Autopilot {
+    fn next_input(&mut self, game: &Game) -> GameInput {
+        self.choose_input(game)
+    }
+}
+
+// Steps from the nearest of `from` to every tile, u32::MAX where unreachable
+fn distances(board: &Board, from: &[BoardPos]) -> Vec<u32> {
+    let mut dist = vec![u32::MAX; board.num_tiles];
+    let mut queue = VecDeque::new();
+    for &tile in from {
+        dist[board.get_tile_of_board_pos(tile)] = 0;
+        queue.push_back(tile);
+    }
+    while let Some(tile) = queue.pop_front() {
+        let d = dist[board.get_tile_of_board_pos(tile)];
+        for dir in Dir::ALL.iter() {
+            let next = match board.neighbor(tile, *dir) {
+                Some(next) if game::pacman_can_enter(board, next) => next,
+                _ => continue,
+            };
+            let h = board.get_tile_of_board_pos(next);
+            if dist[h] == u32::MAX {
+                dist[h] = d + 1;
+                queue.push_back(next);
+            }
+        }
+    }
+    dist
+}
+
+// The first move on the shortest safe path to a tile that `is_target`
+fn first_step_to(
+    board: &Board,
+    start: BoardPos,
+    is_target: impl Fn(BoardPos) -> bool,
+    safe: impl Fn(BoardPos) -> bool,
+) -> Option<Dir> {
+    // each tile remembers the move out of `start` that led to it
+    let mut first = vec![None; board.num_tiles];
+    let mut queue = VecDeque::new();
+    for dir in Dir::ALL.iter() {
+        if let Some(next) = board.neighbor(start, *dir) {
+            if game::pacman_can_enter(board, next) && safe(next) {
+                let h = board.get_tile_of_board_pos(next);
+                if first[h].is_none() {
+                    first[h] = Some(*dir);
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+    while let Some(tile) = queue.pop_front() {
+        let dir = first[board.get_tile_of_board_pos(tile)];
+        if is_target(tile) {
+            return dir;
+        }
+        for d in Dir::ALL.iter() {
+            let next = match board.neighbor(tile, *d) {
+                Some(next)
+                    if next != start && game::pacman_can_enter(board, next) && safe(next) =>
+                {
+                    next
+                }
+                _ => continue,
+            };
+            let h = board.get_tile_of_board_pos(next);
+            if first[h].is_none() {
+                first[h] = dir;
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+// The open neighbour furthest from the ghosts
+fn escape(board: &Board, start: BoardPos, ghost_dist: &[u32]) -> Option<Dir> {
+    Dir::ALL
+        .iter()
+        .cloned()
+        .filter_map(|dir| {
+            let next = board.neighbor(start, dir)?;
+            if !game::pacman_can_enter(board, next) {
+                return None;
+            }
+            Some((ghost_dist[board.get_tile_of_board_pos(next)], dir))
+        })
+        .max_by_key(|&(dist, _)| dist)
+        .map(|(_, dir)| dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Autopilot;
+    use crate::config::Config;
+    use crate::event::GameEvent;
+    use crate::game::{Game, TICKS_PER_SECOND};
+
+    #[test]
+    fn clears_level_one() {
+        // a level takes the bot about a minute, allow it two
+        let tick_limit = 120 * TICKS_PER_SECOND;
+        let autopilot = Autopilot::new();
+        let mut game = Game::new(Config::default(), 1, 1);
+        let mut cleared = false;
+        while !cleared && game.ticks < tick_limit as u64 && !game.is_game_over() {
+            game.tick(autopilot.choose_input(&game));
+            cleared = game
+                .events()
+                .contains(&GameEvent::LevelCleared { level: 1 });
+        }
+        assert!(
+            cleared,
+            "level 1 not cleared by tick {}, level {}, lives {}",
+            game.ticks,
+            game.players.current().level,
+            game.players.current().lives
+        );
+    }
+}
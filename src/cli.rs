@@ -0,0 +1,649 @@
+// Command line flags, shared by both binaries.
+//
+// Rules flags override the cfg file (see `config.rs`), everything else only
+// applies to this run. Anything this doesn't recognise, or that the binary or
+// mode doesn't use, is an error rather than being silently ignored.
+use std::path::PathBuf;
+
+use crate::batch::{Batch, BatchReport};
+use crate::board::Board;
+use crate::bot::Autopilot;
+use crate::config::Config;
+use crate::game::{self, Game};
+use crate::headless::{HeadlessRunner, InputSource, RunSummary};
+use crate::input::GameInput;
+use crate::replay::Replay;
+use crate::scenario::Scenario;
+use crate::stats::{self, GameStats};
+use crate::tile_trace::{self, TickTrace, TileTraceWriter};
+use crate::trace::TraceWriter;
+
+pub const USAGE: &str = "\
+usage: s [flags]
+       gl [flags]
+
+game:
+  --level <n>               level to start on, 1 to 256 (default 1)
+  --seed <n>                RNG seed for new games (default: from the clock)
+  --maze <path>             maze file, 31 lines of 28 tiles:
+                            X wall, . pellet, o power pellet, t tunnel,
+                            - ghost house door, space for an empty tile
+  --scenario <path>         start every game from the situation in a scenario
+                            file: pellets, actors, mode and timers (see
+                            scenario.rs)
+
+rules, these override the cfg file:
+  --config <path>           cfg file to read (default pacman.cfg)
+  --lives <n>               1, 2, 3 or 5
+  --bonus-life <points>     10000, 15000, 20000 or none
+  --difficulty <d>          normal or hard
+  --alternate-ghost-names
+
+display:
+  --scale <n>               window scale, 1 to 8 (default 2)
+  --fullscreen
+
+recording:
+  --record <path>           save the inputs of each game to a replay
+  --replay <path>           play a replay back
+  --verify                  with --replay, check it plays back exactly as it
+                            was recorded and show where it doesn't
+  --state <path>            save state file for F5 / F9 (default pacman.state),
+                            s only
+  --rewind-mb <n>           memory kept for rewinding with backspace (default
+                            16), s only
+
+debugging:
+  --bot                     let the autopilot play instead of the keyboard
+  --debug                   start games paused, P to resume, Space to step
+  --headless                run one game without a window and print how it went
+  --ticks <n>               with --headless or --batch, stop each game after
+                            n ticks
+  --batch <n>               play n games without a window, one per seed from
+                            --seed on (default 0), and report how they went
+  --threads <n>             threads for --batch (default: one per CPU)
+  --stats <dir>             with --headless or --batch, write per-game stats
+                            as CSV files and a heatmap (see stats.rs)
+  --stdio                   no window, take JSON commands on stdin and answer
+                            on stdout, one per line (see protocol.rs)
+  --listen <port>           let inspector tools connect on 127.0.0.1:<port> to
+                            watch and debug the game (see spectator.rs)
+  --trace <path>            write every ghost decision and Pac-Man turn to a
+                            JSON lines file (see trace.rs)
+  --tile-trace <path>       write every actor's tile and the ghost modes after
+                            each tick (see tile_trace.rs)
+  --compare <path>          play like --headless and compare against a
+                            reference tile trace, e.g. from an emulator
+  --help                    show this
+";
+
+// the flags followed by a value
+const VALUE_FLAGS: &[&str] = &[
+    "--config",
+    "--level",
+    "--lives",
+    "--bonus-life",
+    "--difficulty",
+    "--seed",
+    "--maze",
+    "--scenario",
+    "--scale",
+    "--record",
+    "--replay",
+    "--state",
+    "--rewind-mb",
+    "--ticks",
+    "--listen",
+    "--batch",
+    "--threads",
+    "--trace",
+    "--tile-trace",
+    "--compare",
+    "--stats",
+];
+
+// flags that only do anything in a window
+const WINDOW_FLAGS: &[&str] = &[
+    "--scale",
+    "--fullscreen",
+    "--debug",
+    "--listen",
+    "--state",
+    "--rewind-mb",
+];
+
+// flags for what only the sdl binary has: save states and rewinding
+const SDL_ONLY_FLAGS: &[&str] = &["--state", "--rewind-mb"];
+
+// Which binary is parsing, they don't all support the same flags
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Frontend {
+    Sdl,
+    Gl,
+}
+
+pub struct Cli {
+    // the cfg file with the rules flags applied
+    pub config: Config,
+    pub seed: Option<u64>,
+    pub maze: Option<Board>,
+    pub scenario: Option<Scenario>,
+    pub scale: u32,
+    pub fullscreen: bool,
+    pub record: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub verify: bool,
+    pub state: PathBuf,
+    pub rewind_mb: usize,
+    pub bot: bool,
+    pub debug: bool,
+    pub headless: bool,
+    pub ticks: Option<u64>,
+    pub stdio: bool,
+    pub listen: Option<u16>,
+    pub batch: Option<u64>,
+    pub threads: Option<usize>,
+    pub trace: Option<PathBuf>,
+    pub tile_trace: Option<PathBuf>,
+    pub compare: Option<PathBuf>,
+    pub stats: Option<PathBuf>,
+    pub help: bool,
+}
+
+impl Cli {
+    // Parses the process's arguments. Prints the usage and exits for --help,
+    // prints the problem and exits for bad flags.
+    pub fn from_env_or_exit(frontend: Frontend) -> Cli {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        match Cli::parse(&args, frontend) {
+            Ok(cli) if cli.help => {
+                print!("{}", USAGE);
+                std::process::exit(0);
+            }
+            Ok(cli) => cli,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                eprintln!("run with --help to see the flags");
+                std::process::exit(2);
+            }
+        }
+    }
+
+    pub fn parse(args: &[String], frontend: Frontend) -> Result<Cli, String> {
+        // the cfg file goes under the flags, so find it first
+        let config_path = match args.iter().position(|a| a == "--config") {
+            Some(i) => Some(PathBuf::from(value_of(args, i)?)),
+            None => None,
+        };
+
+        let mut cli = Cli {
+            config: Config::load(config_path.as_deref())?,
+            seed: None,
+            maze: None,
+            scenario: None,
+            scale: 2,
+            fullscreen: false,
+            record: None,
+            replay: None,
+            verify: false,
+            state: PathBuf::from("pacman.state"),
+            rewind_mb: 16,
+            bot: false,
+            debug: false,
+            headless: false,
+            ticks: None,
+            stdio: false,
+            listen: None,
+            batch: None,
+            threads: None,
+            trace: None,
+            tile_trace: None,
+            compare: None,
+            stats: None,
+            help: false,
+        };
+
+        // the flags given, without their values
+        let mut given = Vec::new();
+        let mut i = 0;
+        while i < args.len() {
+            let flag = args[i].as_str();
+            given.push(flag);
+            // flags without a value
+            match flag {
+                "--alternate-ghost-names" => cli.config.alternate_ghost_names = true,
+                "--fullscreen" => cli.fullscreen = true,
+                "--bot" => cli.bot = true,
+                "--debug" => cli.debug = true,
+                "--headless" => cli.headless = true,
+                "--stdio" => cli.stdio = true,
+                "--verify" => cli.verify = true,
+                "--help" | "-h" => cli.help = true,
+                _ if !VALUE_FLAGS.contains(&flag) => {
+                    return Err(format!("unknown flag `{}`", flag))
+                }
+                _ => {
+                    let value = value_of(args, i)?;
+                    match flag {
+                        "--config" => {}
+                        "--level" => cli.config.set("start_level", value)?,
+                        "--lives" => cli.config.set("lives", value)?,
+                        "--bonus-life" => cli.config.set("bonus_life", value)?,
+                        "--difficulty" => cli.config.set("difficulty", value)?,
+                        "--seed" => cli.seed = Some(parse_number(flag, value)?),
+                        "--maze" => {
+                            let maze = Board::load(std::path::Path::new(value))?;
+                            game::check_maze(&maze).map_err(|e| format!("{}: {}", value, e))?;
+                            cli.maze = Some(maze);
+                        }
+                        "--scenario" => {
+                            cli.scenario = Some(Scenario::load(std::path::Path::new(value))?)
+                        }
+                        "--scale" => {
+                            cli.scale = parse_number(flag, value)?;
+                            if cli.scale < 1 || cli.scale > 8 {
+                                return Err(format!("--scale must be 1 to 8, got {}", cli.scale));
+                            }
+                        }
+                        "--record" => cli.record = Some(PathBuf::from(value)),
+                        "--replay" => cli.replay = Some(PathBuf::from(value)),
+                        "--state" => cli.state = PathBuf::from(value),
+                        "--rewind-mb" => cli.rewind_mb = parse_number(flag, value)?,
+                        "--ticks" => cli.ticks = Some(parse_number(flag, value)?),
+                        "--listen" => cli.listen = Some(parse_number(flag, value)?),
+                        "--batch" => cli.batch = Some(parse_number(flag, value)?),
+                        "--threads" => cli.threads = Some(parse_number(flag, value)?),
+                        "--trace" => cli.trace = Some(PathBuf::from(value)),
+                        "--tile-trace" => cli.tile_trace = Some(PathBuf::from(value)),
+                        "--compare" => cli.compare = Some(PathBuf::from(value)),
+                        "--stats" => cli.stats = Some(PathBuf::from(value)),
+                        _ => unreachable!(),
+                    }
+                    i += 1;
+                }
+            }
+            i += 1;
+        }
+
+        if frontend == Frontend::Gl {
+            if let Some(flag) = SDL_ONLY_FLAGS.iter().find(|f| given.contains(f)) {
+                return Err(format!("{} only works in the s binary", flag));
+            }
+        }
+
+        // replays and save states only know about the arcade maze
+        if cli.maze.is_some() && (cli.record.is_some() || cli.replay.is_some()) {
+            return Err("--maze can't be used with --record or --replay".to_string());
+        }
+        // and about games from the start
+        if cli.scenario.is_some() && (cli.record.is_some() || cli.replay.is_some()) {
+            return Err("--scenario can't be used with --record or --replay".to_string());
+        }
+        if let Some(scenario) = &cli.scenario {
+            // against this run's maze and rules, so `new_game` can't fail
+            let maze = cli.maze.clone().unwrap_or_default();
+            scenario
+                .apply(&mut Game::with_maze(cli.config, 1, 0, maze))
+                .map_err(|e| format!("--scenario: {}", e))?;
+        }
+        if cli.bot && cli.replay.is_some() {
+            return Err("--bot can't play a --replay".to_string());
+        }
+        if cli.ticks.is_some() && !cli.headless && cli.batch.is_none() && cli.compare.is_none() {
+            return Err(
+                "--ticks only makes sense with --headless, --batch or --compare".to_string(),
+            );
+        }
+        if cli.verify && cli.replay.is_none() {
+            return Err("--verify needs a --replay to check".to_string());
+        }
+        if cli.verify && cli.headless {
+            return Err("--verify can't be used with --headless".to_string());
+        }
+        if cli.stats.is_some() && !cli.headless && cli.batch.is_none() {
+            return Err("--stats only makes sense with --headless or --batch".to_string());
+        }
+        if let Some(games) = cli.batch {
+            batch_seeds(cli.seed.unwrap_or(0), games)?;
+        }
+        if cli.rewind_mb.checked_mul(1024 * 1024).is_none() {
+            return Err(format!(
+                "--rewind-mb {} is more than memory holds",
+                cli.rewind_mb
+            ));
+        }
+        if cli.threads.is_some() && cli.batch.is_none() {
+            return Err("--threads only makes sense with --batch".to_string());
+        }
+        if cli.batch.is_some()
+            && (cli.headless
+                || cli.stdio
+                || cli.listen.is_some()
+                || cli.record.is_some()
+                || cli.replay.is_some())
+        {
+            return Err(
+                "--batch can't be used with --headless, --stdio, --listen, --record or --replay"
+                    .to_string(),
+            );
+        }
+        if cli.stdio && (cli.headless || cli.bot || cli.record.is_some() || cli.replay.is_some()) {
+            return Err(
+                "--stdio can't be used with --headless, --bot, --record or --replay".to_string(),
+            );
+        }
+        if cli.listen.is_some() && (cli.headless || cli.stdio) {
+            return Err("--listen needs a window, not --headless or --stdio".to_string());
+        }
+        if cli.trace.is_some() && (cli.batch.is_some() || cli.stdio || cli.verify) {
+            return Err("--trace can't be used with --batch, --stdio or --verify".to_string());
+        }
+        if cli.tile_trace.is_some() && (cli.batch.is_some() || cli.stdio || cli.verify) {
+            return Err("--tile-trace can't be used with --batch, --stdio or --verify".to_string());
+        }
+        if cli.compare.is_some()
+            && (cli.headless
+                || cli.batch.is_some()
+                || cli.stdio
+                || cli.verify
+                || cli.listen.is_some())
+        {
+            return Err(
+                "--compare can't be used with --headless, --batch, --stdio, --verify or --listen"
+                    .to_string(),
+            );
+        }
+        let windowless =
+            cli.headless || cli.batch.is_some() || cli.stdio || cli.verify || cli.compare.is_some();
+        if windowless {
+            if let Some(flag) = WINDOW_FLAGS.iter().find(|f| given.contains(f)) {
+                return Err(format!(
+                    "{} needs a window, not --headless, --batch, --stdio, --verify or --compare",
+                    flag
+                ));
+            }
+        }
+        Ok(cli)
+    }
+
+    // --seed, or the clock when there isn't one
+    pub fn seed(&self) -> u64 {
+        self.seed.unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or(0)
+        })
+    }
+
+    // A new game with the rules, starting level, maze and scenario from the
+    // command line
+    pub fn new_game(&self, num_players: usize, seed: u64) -> Game {
+        let maze = self.maze.clone().unwrap_or_default();
+        let mut game = Game::with_maze(self.config, num_players, seed, maze);
+        if let Some(scenario) = &self.scenario {
+            scenario
+                .apply(&mut game)
+                .expect("scenarios are checked when the flags are parsed");
+        }
+        game
+    }
+
+    // --headless: play one game (or the --replay) with no window.
+    // Unless it's a replay or --bot nobody is at the joystick and Pac-Man just
+    // sits there.
+    pub fn run_headless(&self) -> Result<RunSummary, String> {
+        let (mut game, seed, mut input, max_ticks) = self.headless_game()?;
+        let mut recording = self
+            .record
+            .as_ref()
+            .map(|_| Replay::new(game.config, game.players.num_players(), seed));
+        let mut trace = self.trace.as_deref().map(TraceWriter::create).transpose()?;
+        game.trace_decisions = trace.is_some();
+        let mut tile_trace = self
+            .tile_trace
+            .as_deref()
+            .map(TileTraceWriter::create)
+            .transpose()?;
+        let mut stats = GameStats::new(seed, &game);
+        let maze = game.maze.clone();
+        let mut trace_error = None;
+        // recordings want the game after each tick along with its input
+        let last_input = std::cell::Cell::new(GameInput::Nil);
+        let mut runner = HeadlessRunner::from_game(game);
+        let summary = runner.run_until(
+            self.ticks.unwrap_or(max_ticks),
+            &mut |game: &Game| {
+                let next = input.next_input(game);
+                last_input.set(next);
+                next
+            },
+            |game| {
+                if let Some(r) = &mut recording {
+                    r.record(last_input.get(), game);
+                }
+                stats.observe(game);
+                let written = trace
+                    .as_mut()
+                    .map_or(Ok(()), |t| t.write_tick(game))
+                    .and_then(|()| tile_trace.as_mut().map_or(Ok(()), |t| t.write_tick(game)));
+                if let Err(e) = written {
+                    trace_error = Some(e);
+                    return true;
+                }
+                false
+            },
+        );
+
+        if let Some(e) = trace_error {
+            return Err(e);
+        }
+        if let Some(trace) = trace {
+            trace.finish()?;
+        }
+        if let Some(tile_trace) = tile_trace {
+            tile_trace.finish()?;
+        }
+
+        if let (Some(recording), Some(path)) = (recording, &self.record) {
+            recording.save(path)?;
+        }
+        if let Some(dir) = &self.stats {
+            stats::export(dir, &maze, &[&stats])?;
+        }
+        Ok(summary)
+    }
+
+    // The game, seed, input and tick limit --headless and --compare play:
+    // the --replay if there is one, otherwise a new game
+    fn headless_game(&self) -> Result<(Game, u64, Box<dyn InputSource>, u64), String> {
+        Ok(match &self.replay {
+            Some(path) => {
+                let replay = Replay::load(path)?;
+                let ticks = replay.inputs.len() as u64;
+                (
+                    replay.new_game(),
+                    replay.seed,
+                    Box::new(replay.playback()),
+                    ticks,
+                )
+            }
+            None => {
+                let seed = self.seed();
+                let input: Box<dyn InputSource> = if self.bot {
+                    Box::new(Autopilot::new())
+                } else {
+                    Box::new(|_: &Game| GameInput::Nil)
+                };
+                (self.new_game(1, seed), seed, input, u64::MAX)
+            }
+        })
+    }
+
+    // --compare: play like --headless, tracing tiles, and compare that with
+    // the reference trace. Returns false if they differ.
+    pub fn compare_trace(&self) -> Result<bool, String> {
+        let path = self.compare.as_ref().ok_or("no --compare trace")?;
+        let reference = tile_trace::load(path)?;
+        let last_tick = reference.last().map_or(0, |t| t.tick);
+        let (game, _, mut input, max_ticks) = self.headless_game()?;
+
+        let mut ours = Vec::new();
+        HeadlessRunner::from_game(game).run_until(
+            self.ticks.unwrap_or(max_ticks).min(last_tick),
+            input.as_mut(),
+            |game| {
+                ours.push(TickTrace::of(game));
+                false
+            },
+        );
+        match tile_trace::compare(&ours, &reference) {
+            None => {
+                println!("{}: {} ticks, all match", path.display(), reference.len());
+                Ok(true)
+            }
+            Some(mismatch) => {
+                print!("{}: {}", path.display(), mismatch);
+                Ok(false)
+            }
+        }
+    }
+
+    // --verify: play the --replay through and say whether it matched.
+    // Returns false if it didn't.
+    pub fn verify_replay(&self) -> Result<bool, String> {
+        let path = self.replay.as_ref().ok_or("no --replay to verify")?;
+        let replay = Replay::load(path)?;
+        match replay.verify() {
+            Ok(()) => {
+                println!(
+                    "{}: {} ticks, all as recorded",
+                    path.display(),
+                    replay.inputs.len()
+                );
+                Ok(true)
+            }
+            Err(desync) => {
+                print!("{}: {}", path.display(), desync);
+                Ok(false)
+            }
+        }
+    }
+
+    // --batch: `games` games on `threads` threads, unless --threads says
+    // otherwise. Without --bot nobody plays, which is only good for testing
+    // the ghosts.
+    pub fn run_batch(&self, games: u64, threads: usize) -> Result<BatchReport, String> {
+        let seeds = batch_seeds(self.seed.unwrap_or(0), games)?;
+        let mut batch = Batch::new(seeds, self.threads.unwrap_or(threads));
+        if let Some(ticks) = self.ticks {
+            batch.max_ticks = ticks;
+        }
+        let bot = self.bot;
+        let report = batch.run(
+            |seed| self.new_game(1, seed),
+            || {
+                move |game: &Game| {
+                    if bot {
+                        Autopilot::new().choose_input(game)
+                    } else {
+                        GameInput::Nil
+                    }
+                }
+            },
+        );
+        if let Some(dir) = &self.stats {
+            let maze = self.maze.clone().unwrap_or_default();
+            let games: Vec<&GameStats> = report.games.iter().map(|g| &g.stats).collect();
+            stats::export(dir, &maze, &games)?;
+        }
+        Ok(report)
+    }
+}
+
+fn value_of(args: &[String], i: usize) -> Result<&str, String> {
+    args.get(i + 1)
+        .map(|v| v.as_str())
+        .ok_or_else(|| format!("{} expects a value", args[i]))
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} expects a number, got `{}`", flag, value))
+}
+
+// --batch's seeds, from --seed on
+fn batch_seeds(first: u64, games: u64) -> Result<std::ops::Range<u64>, String> {
+    match first.checked_add(games) {
+        Some(end) => Ok(first..end),
+        None => Err(format!(
+            "--seed {} and --batch {} run past the last seed",
+            first, games
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cli, Frontend};
+
+    fn parse(args: &str, frontend: Frontend) -> Result<Cli, String> {
+        let args: Vec<String> = args.split_whitespace().map(str::to_string).collect();
+        Cli::parse(&args, frontend)
+    }
+
+    fn error(args: &str) -> String {
+        parse(args, Frontend::Sdl).err().unwrap()
+    }
+
+    #[test]
+    fn accepts_a_normal_run() {
+        let cli = parse("--level 3 --seed 9 --scale 3 --debug --bot", Frontend::Sdl).unwrap();
+        assert_eq!(cli.config.start_level, 3);
+        assert_eq!(cli.seed, Some(9));
+        assert!(cli.debug && cli.bot);
+        assert!(parse("--batch 10 --bot --threads 2", Frontend::Gl).is_ok());
+    }
+
+    #[test]
+    fn rejects_bad_flags_and_values() {
+        assert_eq!(error("--nope"), "unknown flag `--nope`");
+        assert!(error("--seed").contains("--seed"));
+        assert_eq!(error("--scale 9"), "--scale must be 1 to 8, got 9");
+        assert!(error("--level 0").starts_with("start_level must be"));
+        assert!(error("--level 4294967295").starts_with("start_level must be"));
+        assert!(error("--lives 4").starts_with("lives must be"));
+        assert!(error("--rewind-mb 18446744073709551615").contains("--rewind-mb"));
+        assert!(error("--seed 18446744073709551615 --batch 1").contains("run past the last seed"));
+    }
+
+    #[test]
+    fn rejects_flags_the_mode_ignores() {
+        for mode in ["--headless", "--stdio", "--batch 5", "--compare trace.txt"].iter() {
+            let e = error(&format!("{} --debug", mode));
+            assert!(e.starts_with("--debug needs a window"), "{}: {}", mode, e);
+        }
+        assert!(error("--headless --scale 2").starts_with("--scale needs a window"));
+        assert!(error("--batch 5 --state x").starts_with("--state needs a window"));
+        assert_eq!(
+            error("--ticks 10"),
+            "--ticks only makes sense with --headless, --batch or --compare"
+        );
+        assert_eq!(error("--verify"), "--verify needs a --replay to check");
+        assert_eq!(
+            error("--threads 2"),
+            "--threads only makes sense with --batch"
+        );
+        assert_eq!(error("--bot --replay r.bin"), "--bot can't play a --replay");
+    }
+
+    #[test]
+    fn rejects_flags_the_binary_ignores() {
+        for flag in ["--state x", "--rewind-mb 4"].iter() {
+            assert!(parse(flag, Frontend::Sdl).is_ok());
+            let e = parse(flag, Frontend::Gl).err().unwrap();
+            assert!(e.ends_with("only works in the s binary"), "{}", e);
+        }
+    }
+}
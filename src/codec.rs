@@ -0,0 +1,109 @@
+// Little-endian byte packing for the binary file formats (replays, save states).
+pub struct Writer {
+    pub bytes: Vec<u8>,
+}
+
+impl Default for Writer {
+    fn default() -> Writer {
+        Writer::new()
+    }
+}
+
+impl Writer {
+    pub fn new() -> Writer {
+        Writer { bytes: Vec::new() }
+    }
+
+    pub fn raw(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    pub fn u8(&mut self, v: u8) {
+        self.bytes.push(v);
+    }
+
+    pub fn bool(&mut self, v: bool) {
+        self.u8(v as u8);
+    }
+
+    pub fn u16(&mut self, v: u16) {
+        self.raw(&v.to_le_bytes());
+    }
+
+    pub fn u32(&mut self, v: u32) {
+        self.raw(&v.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, v: u64) {
+        self.raw(&v.to_le_bytes());
+    }
+
+    pub fn i32(&mut self, v: i32) {
+        self.raw(&v.to_le_bytes());
+    }
+}
+
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Reader<'a> {
+        Reader { bytes, pos: 0 }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+
+    // bytes not read yet
+    pub fn remaining(&self) -> usize {
+        self.bytes.len().saturating_sub(self.pos)
+    }
+
+    pub fn raw(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if self.pos + len > self.bytes.len() {
+            return Err(format!("unexpected end of data at byte {}", self.pos));
+        }
+        let bytes = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.raw(1)?[0])
+    }
+
+    pub fn bool(&mut self) -> Result<bool, String> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            v => Err(format!("bad bool {} at byte {}", v, self.pos - 1)),
+        }
+    }
+
+    pub fn u16(&mut self) -> Result<u16, String> {
+        let mut b = [0; 2];
+        b.copy_from_slice(self.raw(2)?);
+        Ok(u16::from_le_bytes(b))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, String> {
+        let mut b = [0; 4];
+        b.copy_from_slice(self.raw(4)?);
+        Ok(u32::from_le_bytes(b))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, String> {
+        let mut b = [0; 8];
+        b.copy_from_slice(self.raw(8)?);
+        Ok(u64::from_le_bytes(b))
+    }
+
+    pub fn i32(&mut self) -> Result<i32, String> {
+        let mut b = [0; 4];
+        b.copy_from_slice(self.raw(4)?);
+        Ok(i32::from_le_bytes(b))
+    }
+}
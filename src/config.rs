@@ -0,0 +1,305 @@
+// Operator settings, the arcade's DIP switches.
+//
+// Settings are read from `pacman.cfg` (or the file given with `--config`) and then
+// overridden by command line flags (see `cli.rs`), so both binaries can share a
+// cfg file.
+//
+// pacman.cfg:
+//   # comments start with '#'
+//   lives = 5
+//   bonus_life = 15000     # 10000, 15000, 20000 or none
+//   difficulty = hard      # normal or hard
+//   alternate_ghost_names = true
+//   start_level = 1
+
+use crate::codec::{Reader, Writer};
+use crate::level::LAST_LEVEL;
+
+pub const DEFAULT_CONFIG_PATH: &str = "pacman.cfg";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Difficulty {
+    Normal,
+    Hard,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Config {
+    pub lives: u32,
+    // `None` means no bonus life is awarded
+    pub bonus_life: Option<u32>,
+    pub difficulty: Difficulty,
+    pub alternate_ghost_names: bool,
+    // not a DIP switch, but replays need it to play the same game
+    pub start_level: u32,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            lives: 3,
+            bonus_life: Some(10_000),
+            difficulty: Difficulty::Normal,
+            alternate_ghost_names: false,
+            start_level: 1,
+        }
+    }
+}
+
+impl Config {
+    // Defaults <- cfg file. A missing default cfg file is fine, a missing
+    // explicit one is not.
+    pub fn load(path: Option<&std::path::Path>) -> Result<Config, String> {
+        let mut config = Config::default();
+        match path {
+            Some(path) => config.apply_file(path)?,
+            None => {
+                let path = std::path::Path::new(DEFAULT_CONFIG_PATH);
+                if path.exists() {
+                    config.apply_file(path)?;
+                }
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn apply_file(&mut self, path: &std::path::Path) -> Result<(), String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+        self.apply_cfg_str(&contents)
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn apply_cfg_str(&mut self, contents: &str) -> Result<(), String> {
+        for (line_num, line) in contents.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next().unwrap().trim();
+            let value = parts
+                .next()
+                .ok_or_else(|| format!("line {}: expected `key = value`", line_num + 1))?
+                .trim();
+            self.set(key, value)
+                .map_err(|e| format!("line {}: {}", line_num + 1, e))?;
+        }
+        Ok(())
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "lives" => {
+                self.lives = match value {
+                    "1" => 1,
+                    "2" => 2,
+                    "3" => 3,
+                    "5" => 5,
+                    _ => return Err(format!("lives must be 1, 2, 3 or 5, got `{}`", value)),
+                }
+            }
+            "bonus_life" => {
+                self.bonus_life = match value {
+                    "10000" => Some(10_000),
+                    "15000" => Some(15_000),
+                    "20000" => Some(20_000),
+                    "none" => None,
+                    _ => {
+                        return Err(format!(
+                            "bonus_life must be 10000, 15000, 20000 or none, got `{}`",
+                            value
+                        ))
+                    }
+                }
+            }
+            "difficulty" => {
+                self.difficulty = match value {
+                    "normal" => Difficulty::Normal,
+                    "hard" => Difficulty::Hard,
+                    _ => {
+                        return Err(format!(
+                            "difficulty must be normal or hard, got `{}`",
+                            value
+                        ))
+                    }
+                }
+            }
+            "alternate_ghost_names" => {
+                self.alternate_ghost_names = match value {
+                    "true" => true,
+                    "false" => false,
+                    _ => {
+                        return Err(format!(
+                            "alternate_ghost_names must be true or false, got `{}`",
+                            value
+                        ))
+                    }
+                }
+            }
+            "start_level" => {
+                self.start_level = match value.parse() {
+                    Ok(level) if (1..=LAST_LEVEL).contains(&level) => level,
+                    _ => {
+                        return Err(format!(
+                            "start_level must be a number from 1 to {}, got `{}`",
+                            LAST_LEVEL, value
+                        ))
+                    }
+                }
+            }
+            _ => return Err(format!("unknown setting `{}`", key)),
+        }
+        Ok(())
+    }
+
+    // for replays and save states, which have to play by the same rules
+    pub fn write(&self, w: &mut Writer) {
+        w.u8(self.lives as u8);
+        w.u32(self.bonus_life.unwrap_or(0));
+        w.u8(match self.difficulty {
+            Difficulty::Normal => 0,
+            Difficulty::Hard => 1,
+        });
+        w.bool(self.alternate_ghost_names);
+        w.u32(self.start_level);
+    }
+
+    pub fn read(r: &mut Reader) -> Result<Config, String> {
+        let lives = r.u8()? as u32;
+        if lives == 0 {
+            return Err("bad number of lives 0".to_string());
+        }
+        let bonus_life = match r.u32()? {
+            0 => None,
+            points => Some(points),
+        };
+        let difficulty = match r.u8()? {
+            0 => Difficulty::Normal,
+            1 => Difficulty::Hard,
+            v => return Err(format!("bad difficulty {}", v)),
+        };
+        let alternate_ghost_names = r.bool()?;
+        let start_level = r.u32()?;
+        if !(1..=LAST_LEVEL).contains(&start_level) {
+            return Err(format!("bad start level {}", start_level));
+        }
+        Ok(Config {
+            lives,
+            bonus_life,
+            difficulty,
+            alternate_ghost_names,
+            start_level,
+        })
+    }
+
+    // (character, nickname) for blinky, pinky, inky and clyde
+    pub fn ghost_names(&self) -> [(&'static str, &'static str); 4] {
+        if self.alternate_ghost_names {
+            [
+                ("URCHIN", "MACKY"),
+                ("ROMP", "MICKY"),
+                ("STYLIST", "MUCKY"),
+                ("CRYBABY", "MOCKY"),
+            ]
+        } else {
+            [
+                ("SHADOW", "BLINKY"),
+                ("SPEEDY", "PINKY"),
+                ("BASHFUL", "INKY"),
+                ("POKEY", "CLYDE"),
+            ]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Config, Difficulty};
+    use crate::codec::{Reader, Writer};
+
+    fn written(config: &Config) -> Vec<u8> {
+        let mut w = Writer::new();
+        config.write(&mut w);
+        w.bytes
+    }
+
+    #[test]
+    fn parses_and_round_trips() {
+        let mut config = Config::default();
+        config
+            .apply_cfg_str(
+                "# operator settings\n\
+                 lives = 5\n\
+                 bonus_life = none   # no extra pac-man\n\
+                 \n\
+                 difficulty = hard\n\
+                 alternate_ghost_names = true\n\
+                 start_level = 256\n",
+            )
+            .unwrap();
+        let expected = Config {
+            lives: 5,
+            bonus_life: None,
+            difficulty: Difficulty::Hard,
+            alternate_ghost_names: true,
+            start_level: 256,
+        };
+        assert_eq!(config, expected);
+
+        let bytes = written(&config);
+        let mut r = Reader::new(&bytes);
+        assert_eq!(Config::read(&mut r), Ok(expected));
+        assert!(r.is_empty());
+    }
+
+    #[test]
+    fn rejects_bad_settings() {
+        for (cfg, error) in [
+            ("lives = 4", "line 1: lives must be 1, 2, 3 or 5, got `4`"),
+            (
+                "\nbonus_life = 5000",
+                "line 2: bonus_life must be 10000, 15000, 20000 or none, got `5000`",
+            ),
+            (
+                "difficulty = easy",
+                "line 1: difficulty must be normal or hard, got `easy`",
+            ),
+            (
+                "alternate_ghost_names = yes",
+                "line 1: alternate_ghost_names must be true or false, got `yes`",
+            ),
+            (
+                "start_level = 0",
+                "line 1: start_level must be a number from 1 to 256, got `0`",
+            ),
+            (
+                "start_level = 257",
+                "line 1: start_level must be a number from 1 to 256, got `257`",
+            ),
+            ("speed = 2", "line 1: unknown setting `speed`"),
+            ("lives", "line 1: expected `key = value`"),
+        ] {
+            let mut config = Config::default();
+            assert_eq!(config.apply_cfg_str(cfg), Err(error.to_string()), "{}", cfg);
+        }
+    }
+
+    #[test]
+    fn read_rejects_what_set_would() {
+        let read = |config: Config| Config::read(&mut Reader::new(&written(&config)));
+        let no_lives = Config {
+            lives: 0,
+            ..Config::default()
+        };
+        assert_eq!(read(no_lives), Err("bad number of lives 0".to_string()));
+        let past_the_last_level = Config {
+            start_level: 257,
+            ..Config::default()
+        };
+        assert_eq!(
+            read(past_the_last_level),
+            Err("bad start level 257".to_string())
+        );
+    }
+}
@@ -0,0 +1,154 @@
+// The coffee break intermissions.
+//
+// A cutscene is a fixed timeline: every actor has a list of segments, each one
+// moving a sprite in a straight line between two points over a range of ticks.
+// Actors outside any segment are off screen. Nothing here touches the maze.
+use crate::vec2::{Fixed, Vec2, SUBPIXELS};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Intermission {
+    // Blinky chases Pac-Man off screen, a giant Pac-Man chases him back
+    GiantPacman,
+    // Blinky snags his cloak on a nail and tears it
+    TornCloak,
+    // Blinky's cloak is patched, then he drags it back naked
+    PatchedCloak,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sprite {
+    Pacman,
+    GiantPacman,
+    Blinky,
+    FrightenedBlinky,
+    BlinkyTornCloak,
+    BlinkyPatchedCloak,
+    BlinkyNaked,
+    Nail,
+}
+
+struct Segment {
+    start: u32,
+    end: u32,
+    // in pixels
+    from: (i32, i32),
+    to: (i32, i32),
+    sprite: Sprite,
+}
+
+// everything happens on one row, a little below the middle of the screen
+const Y: i32 = 136;
+
+const fn seg(start: u32, end: u32, from_x: i32, to_x: i32, sprite: Sprite) -> Segment {
+    Segment {
+        start,
+        end,
+        from: (from_x, Y),
+        to: (to_x, Y),
+        sprite,
+    }
+}
+
+const GIANT_PACMAN: &[&[Segment]] = &[
+    &[seg(0, 240, 240, -16, Sprite::Pacman)],
+    &[
+        seg(20, 250, 256, -16, Sprite::Blinky),
+        seg(300, 520, -16, 240, Sprite::FrightenedBlinky),
+    ],
+    &[seg(340, 600, -48, 240, Sprite::GiantPacman)],
+];
+const GIANT_PACMAN_TICKS: u32 = 620;
+
+const TORN_CLOAK: &[&[Segment]] = &[
+    &[seg(0, 400, 112, 112, Sprite::Nail)],
+    &[seg(0, 200, 240, -16, Sprite::Pacman)],
+    &[
+        seg(60, 160, 256, 116, Sprite::Blinky),
+        // caught on the nail, straining forward
+        seg(160, 260, 116, 108, Sprite::Blinky),
+        seg(260, 400, 108, 108, Sprite::BlinkyTornCloak),
+    ],
+];
+const TORN_CLOAK_TICKS: u32 = 420;
+
+const PATCHED_CLOAK: &[&[Segment]] = &[
+    &[seg(0, 220, 240, -16, Sprite::Pacman)],
+    &[
+        seg(30, 260, 256, -16, Sprite::BlinkyPatchedCloak),
+        seg(330, 560, -16, 240, Sprite::BlinkyNaked),
+    ],
+];
+const PATCHED_CLOAK_TICKS: u32 = 600;
+
+impl Intermission {
+    // Which intermission, if any, follows clearing `level`.
+    // The third one repeats after levels 13 and 17.
+    pub fn after_level(level: u32) -> Option<Intermission> {
+        match level {
+            2 => Some(Intermission::GiantPacman),
+            5 => Some(Intermission::TornCloak),
+            9 | 13 | 17 => Some(Intermission::PatchedCloak),
+            _ => None,
+        }
+    }
+
+    fn script(self) -> (&'static [&'static [Segment]], u32) {
+        match self {
+            Intermission::GiantPacman => (GIANT_PACMAN, GIANT_PACMAN_TICKS),
+            Intermission::TornCloak => (TORN_CLOAK, TORN_CLOAK_TICKS),
+            Intermission::PatchedCloak => (PATCHED_CLOAK, PATCHED_CLOAK_TICKS),
+        }
+    }
+}
+
+// A playing intermission
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cutscene {
+    pub intermission: Intermission,
+    pub ticks: u32,
+}
+
+impl Cutscene {
+    pub fn new(intermission: Intermission) -> Cutscene {
+        Cutscene {
+            intermission,
+            ticks: 0,
+        }
+    }
+
+    pub fn tick(&mut self) {
+        if !self.is_finished() {
+            self.ticks += 1;
+        }
+    }
+
+    pub fn skip(&mut self) {
+        self.ticks = self.intermission.script().1;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.ticks >= self.intermission.script().1
+    }
+
+    // The sprites on screen right now and their positions
+    pub fn actors(&self) -> Vec<(Sprite, Vec2)> {
+        let (tracks, _) = self.intermission.script();
+        tracks
+            .iter()
+            .filter_map(|track| {
+                track
+                    .iter()
+                    .find(|s| s.start <= self.ticks && self.ticks < s.end)
+            })
+            .map(|s| {
+                let (elapsed, duration) = ((self.ticks - s.start) as i32, (s.end - s.start) as i32);
+                let along = |from: i32, to: i32| {
+                    Fixed::pixels(from)
+                        + Fixed::from_raw((to - from) * SUBPIXELS * elapsed / duration)
+                };
+                let pos = Vec2::new(along(s.from.0, s.to.0), along(s.from.1, s.to.1));
+                (s.sprite, pos)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,144 @@
+// Pause / step / run-until controls for debugging the simulation.
+//
+// The front-end asks `should_tick` before every tick it would normally run, so
+// while paused the game only moves when told to, and calls `after_tick` after
+// each one so "run until X happens" can stop on the tick X happened.
+use crate::event::GameEvent;
+use crate::game::Game;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopCondition {
+    PelletEaten,
+    PowerPelletEaten,
+    GhostEaten,
+    PacmanDied,
+    LevelCleared,
+    ModeChanged,
+    FruitSpawned,
+}
+
+impl StopCondition {
+    fn is_met_by(self, event: &GameEvent) -> bool {
+        match (self, event) {
+            (StopCondition::PelletEaten, GameEvent::PelletEaten { .. }) => true,
+            (StopCondition::PowerPelletEaten, GameEvent::EnergizerEaten { .. }) => true,
+            (StopCondition::GhostEaten, GameEvent::GhostEaten { .. }) => true,
+            (StopCondition::PacmanDied, GameEvent::PacmanDied { .. }) => true,
+            (StopCondition::LevelCleared, GameEvent::LevelCleared { .. }) => true,
+            // frightened counts as a mode here
+            (StopCondition::ModeChanged, GameEvent::ModeChanged { .. })
+            | (StopCondition::ModeChanged, GameEvent::EnergizerEaten { .. })
+            | (StopCondition::ModeChanged, GameEvent::FrightEnded) => true,
+            (StopCondition::FruitSpawned, GameEvent::FruitSpawned { .. }) => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Debugger {
+    pub paused: bool,
+    steps_pending: u32,
+    run_until: Option<StopCondition>,
+    // step count being typed in
+    count: Option<u32>,
+}
+
+impl Debugger {
+    pub fn new() -> Debugger {
+        Debugger::default()
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.steps_pending = 0;
+        self.run_until = None;
+        self.count = None;
+    }
+
+    // Pause (if we weren't already) and then run exactly `n` more ticks
+    pub fn step(&mut self, n: u32) {
+        self.paused = true;
+        self.run_until = None;
+        self.steps_pending = self.steps_pending.saturating_add(n);
+    }
+
+    // Run until `condition` happens, then pause
+    pub fn run_until(&mut self, condition: StopCondition) {
+        self.paused = true;
+        self.steps_pending = 0;
+        self.run_until = Some(condition);
+    }
+
+    pub fn running_until(&self) -> Option<StopCondition> {
+        self.run_until
+    }
+
+    // Ticks still to run from `step`
+    pub fn steps_pending(&self) -> u32 {
+        self.steps_pending
+    }
+
+    pub fn should_tick(&mut self) -> bool {
+        if !self.paused || self.run_until.is_some() {
+            return true;
+        }
+        if self.steps_pending > 0 {
+            self.steps_pending -= 1;
+            return true;
+        }
+        false
+    }
+
+    pub fn after_tick(&mut self, game: &Game) {
+        if let Some(condition) = self.run_until {
+            if game.events().iter().any(|e| condition.is_met_by(e)) {
+                self.run_until = None;
+            }
+        }
+    }
+}
+
+// Debug key bindings, shared by both binaries:
+//   P               pause / resume
+//   Space           step one tick (`GameInput::Step`)
+//   digits, Space   step that many ticks, e.g. `120` then Space
+//   F1..F4, F6..F8  run until: pellet, power pellet, ghost eaten, death,
+//                   level cleared, mode change, fruit
+#[cfg(feature = "sdl")]
+impl Debugger {
+    // Returns true if the key was a debugger key
+    pub fn handle_key(&mut self, keycode: sdl2::keyboard::Keycode) -> bool {
+        use crate::input::GameInput;
+        use sdl2::keyboard::Keycode;
+
+        if GameInput::from(keycode) == GameInput::Step {
+            let n = self.count.take().unwrap_or(1);
+            self.step(n);
+            return true;
+        }
+        // digits only mean a step count while paused
+        let digit = keycode.into_i32() - Keycode::Num0.into_i32();
+        if self.paused && (0..=9).contains(&digit) {
+            let count = self.count.unwrap_or(0);
+            self.count = Some(count.saturating_mul(10).saturating_add(digit as u32));
+            return true;
+        }
+        let condition = match keycode {
+            Keycode::P => {
+                self.toggle_pause();
+                return true;
+            }
+            Keycode::F1 => StopCondition::PelletEaten,
+            Keycode::F2 => StopCondition::PowerPelletEaten,
+            Keycode::F3 => StopCondition::GhostEaten,
+            Keycode::F4 => StopCondition::PacmanDied,
+            Keycode::F6 => StopCondition::LevelCleared,
+            Keycode::F7 => StopCondition::ModeChanged,
+            Keycode::F8 => StopCondition::FruitSpawned,
+            _ => return false,
+        };
+        self.run_until(condition);
+        true
+    }
+}
@@ -0,0 +1,158 @@
+// Catching desyncs: a game that doesn't play out the same way twice.
+//
+// Replays carry a hash of the whole game state after every tick, and a full
+// save state every few seconds. Playing one back and comparing hashes finds
+// the first tick where the simulation went a different way, which is where to
+// look when a refactor was supposed to change nothing. The hash is FNV-1a over
+// the save state bytes, so it covers exactly what the next tick depends on.
+use std::fmt;
+
+use crate::game::{Game, GhostKind};
+use crate::savestate;
+
+pub fn state_hash(game: &Game) -> u64 {
+    fnv1a(&savestate::to_bytes(game))
+}
+
+// 64 bit FNV-1a
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &b in bytes {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+// Where a playback went its own way
+#[derive(Clone, Debug, PartialEq)]
+pub struct Desync {
+    // the first tick whose state didn't match, counting from 1
+    pub tick: u64,
+    // the first full state recorded from then on, and how the playback
+    // differed from it
+    pub compared_tick: Option<u64>,
+    pub diff: Vec<String>,
+}
+
+impl fmt::Display for Desync {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "desync at tick {}", self.tick)?;
+        match self.compared_tick {
+            Some(tick) if self.diff.is_empty() => {
+                writeln!(f, "but the state at tick {} matches again", tick)
+            }
+            Some(tick) => {
+                writeln!(f, "at tick {}, expected vs played back:", tick)?;
+                for line in self.diff.iter() {
+                    writeln!(f, "  {}", line)?;
+                }
+                Ok(())
+            }
+            None => writeln!(f, "no full state was recorded after it to compare with"),
+        }
+    }
+}
+
+// the most pellet differences listed, the rest are counted
+const MAX_PELLET_LINES: usize = 10;
+
+// Everything that differs between two games, one line per field
+pub fn diff(expected: &Game, actual: &Game) -> Vec<String> {
+    let mut lines = Vec::new();
+    // pellets go last, after every other field
+    let mut tiles = Vec::new();
+    let mut field = |name: &str, a: &dyn fmt::Debug, b: &dyn fmt::Debug| {
+        let (a, b) = (format!("{:?}", a), format!("{:?}", b));
+        if a != b {
+            lines.push(format!("{}: {} vs {}", name, a, b));
+        }
+    };
+
+    field("ticks", &expected.ticks, &actual.ticks);
+    field("phase", &expected.phase, &actual.phase);
+    field("rng", &expected.rng.state, &actual.rng.state);
+    field("mode", &expected.mode, &actual.mode);
+    field("mode_index", &expected.mode_index, &actual.mode_index);
+    field("mode_ticks", &expected.mode_ticks, &actual.mode_ticks);
+    field("fright_ticks", &expected.fright_ticks, &actual.fright_ticks);
+    field("ghosts_eaten", &expected.ghosts_eaten, &actual.ghosts_eaten);
+    field(
+        "house_pellets",
+        &expected.house_pellets,
+        &actual.house_pellets,
+    );
+    field(
+        "house_idle_ticks",
+        &expected.house_idle_ticks,
+        &actual.house_idle_ticks,
+    );
+    field(
+        "life_lost_this_level",
+        &expected.life_lost_this_level,
+        &actual.life_lost_this_level,
+    );
+    field("bonus_fruit", &expected.bonus_fruit, &actual.bonus_fruit);
+
+    field("pacman.pos", &expected.pacman.pos, &actual.pacman.pos);
+    field("pacman.dir", &expected.pacman.dir, &actual.pacman.dir);
+    field(
+        "pacman.wanted_dir",
+        &expected.pacman.wanted_dir,
+        &actual.pacman.wanted_dir,
+    );
+    for kind in GhostKind::ALL.iter() {
+        let (a, b) = (&expected.ghosts[kind.index()], &actual.ghosts[kind.index()]);
+        let name = format!("{:?}", kind).to_lowercase();
+        field(&format!("{}.pos", name), &a.pos, &b.pos);
+        field(&format!("{}.dir", name), &a.dir, &b.dir);
+        field(&format!("{}.state", name), &a.state, &b.state);
+        field(
+            &format!("{}.frightened", name),
+            &a.frightened,
+            &b.frightened,
+        );
+    }
+
+    let (a, b) = (&expected.players, &actual.players);
+    field("players", &a.num_players(), &b.num_players());
+    field("current player", &a.current_index(), &b.current_index());
+    for i in 0..a.num_players().min(b.num_players()) {
+        let (a, b) = (a.get(i), b.get(i));
+        let name = format!("player {}", i + 1);
+        field(&format!("{} level", name), &a.level, &b.level);
+        field(&format!("{} score", name), &a.score, &b.score);
+        field(&format!("{} lives", name), &a.lives, &b.lives);
+        field(
+            &format!("{} bonus life", name),
+            &a.bonus_life_awarded,
+            &b.bonus_life_awarded,
+        );
+
+        let mut pellets = Vec::new();
+        for h in 0..a.board.num_tiles.min(b.board.num_tiles) {
+            let describe = |pellet: bool, power: bool| match (pellet, power) {
+                (true, _) => "pellet",
+                (_, true) => "power pellet",
+                _ => "empty",
+            };
+            let was = describe(a.board.has_pellet[h], a.board.has_power_pellet[h]);
+            let is = describe(b.board.has_pellet[h], b.board.has_power_pellet[h]);
+            if was != is {
+                let pos = a.board.get_board_pos_of_tile(h);
+                pellets.push(format!(
+                    "{} tile ({}, {}): {} vs {}",
+                    name, pos.x, pos.y, was, is
+                ));
+            }
+        }
+        let more = pellets.len().saturating_sub(MAX_PELLET_LINES);
+        pellets.truncate(MAX_PELLET_LINES);
+        tiles.extend(pellets);
+        if more > 0 {
+            tiles.push(format!("{} and {} more tiles", name, more));
+        }
+    }
+    lines.extend(tiles);
+    lines
+}
@@ -0,0 +1,45 @@
+// Things that happened during a tick.
+//
+// The game collects these as it simulates, so audio, rendering and stats can
+// react to them instead of comparing the game's state from one tick to the
+// next. They only describe the last tick: `Game::tick` clears them first.
+use crate::board::BoardPos;
+use crate::game::{Game, GhostKind, GhostMode};
+use crate::level::Fruit;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameEvent {
+    PelletEaten { tile: BoardPos },
+    // the power pellets in the corners
+    EnergizerEaten { tile: BoardPos },
+    GhostEaten { ghost: GhostKind, points: u32 },
+    // caught by `ghost`
+    PacmanDied { ghost: GhostKind },
+    FruitSpawned { fruit: Fruit },
+    FruitEaten { fruit: Fruit, points: u32 },
+    ExtraLife,
+    LevelCleared { level: u32 },
+    // scatter <-> chase, frightened mode starts with `EnergizerEaten`
+    ModeChanged { mode: GhostMode },
+    FrightEnded,
+}
+
+pub trait EventListener {
+    // `game` is the state after the tick the event happened in
+    fn on_event(&mut self, game: &Game, event: &GameEvent);
+}
+
+impl<F: FnMut(&Game, &GameEvent)> EventListener for F {
+    fn on_event(&mut self, game: &Game, event: &GameEvent) {
+        self(game, event)
+    }
+}
+
+// Hand the last tick's events to each listener in turn
+pub fn dispatch(game: &Game, listeners: &mut [&mut dyn EventListener]) {
+    for event in game.events() {
+        for listener in listeners.iter_mut() {
+            listener.on_event(game, event);
+        }
+    }
+}
@@ -0,0 +1,982 @@
+// The game simulation.
+//
+// Everything here is deterministic: the same config, seed and sequence of
+// `GameInput`s always plays out the same game. Nothing in here knows about SDL,
+// timing or rendering, callers just call `Game::tick` once per arcade frame.
+//
+// Positions are fixed point pixels (see `vec2.rs`), the top-left of the actor's
+// 8x8 cell, so an actor is centered on a tile when its position is a multiple
+// of `Board::TILE_WIDTH`. Actors only change direction on a tile center.
+use std::sync::Arc;
+
+use crate::board::{Board, BoardPos, Dir};
+use crate::config::Config;
+use crate::cutscene::{Cutscene, Intermission};
+use crate::event::GameEvent;
+use crate::ghost_ai::{self, GhostAi, GhostView};
+use crate::input::GameInput;
+use crate::level::{level_spec, Fruit, LevelSpec};
+use crate::player::{Player, Players};
+use crate::rng::Rng;
+use crate::trace::{self, Decision, GhostDecision, TraceMode};
+use crate::vec2::{Fixed, Vec2, SUBPIXELS};
+
+// The arcade runs its game logic once per video frame
+pub const TICKS_PER_SECOND: u32 = 60;
+
+// 100% in the Dossier's speed tables, 1.25 px per tick, ~75.76 px/s
+pub const MAX_SPEED: Fixed = Fixed::from_raw(SUBPIXELS * 5 / 4);
+const EYES_SPEED: Fixed = MAX_SPEED.percent(200);
+const HOUSE_SPEED: Fixed = MAX_SPEED.percent(50);
+// in fixed point
+const TILE: Fixed = Fixed::pixels(Board::TILE_WIDTH);
+
+const READY_TICKS: u32 = 2 * TICKS_PER_SECOND;
+const DYING_TICKS: u32 = 2 * TICKS_PER_SECOND;
+const LEVEL_COMPLETE_TICKS: u32 = 2 * TICKS_PER_SECOND;
+
+// bonus fruit appears after this many pellets and stays for ~9.5 seconds
+const FRUIT_PELLETS: [usize; 2] = [70, 170];
+const FRUIT_TICKS: u32 = 570;
+
+// if Pac-Man stops eating for this long the next ghost is let out of the house
+const HOUSE_IDLE_RELEASE_TICKS: u32 = 4 * TICKS_PER_SECOND;
+
+const PELLET_POINTS: u32 = 10;
+const POWER_PELLET_POINTS: u32 = 50;
+const FIRST_GHOST_POINTS: u32 = 200;
+
+pub const PACMAN_START: BoardPos = BoardPos { x: 13, y: 23 };
+// the tile just above the ghost house door
+pub const HOUSE_EXIT: BoardPos = BoardPos { x: 13, y: 11 };
+pub const HOUSE_CENTER: BoardPos = BoardPos { x: 13, y: 14 };
+pub const FRUIT_POS: BoardPos = BoardPos { x: 13, y: 17 };
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GhostKind {
+    Blinky,
+    Pinky,
+    Inky,
+    Clyde,
+}
+
+impl GhostKind {
+    pub const ALL: [GhostKind; 4] = [
+        GhostKind::Blinky,
+        GhostKind::Pinky,
+        GhostKind::Inky,
+        GhostKind::Clyde,
+    ];
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    // Targets are in tiles and may be off the board.
+    // Our board doesn't have the 3 rows of score above the maze the Dossier counts.
+    pub fn scatter_target(self) -> (i32, i32) {
+        match self {
+            GhostKind::Blinky => (25, -3),
+            GhostKind::Pinky => (2, -3),
+            GhostKind::Inky => (27, 31),
+            GhostKind::Clyde => (0, 31),
+        }
+    }
+
+    // where the ghost waits inside the house
+    pub fn home(self) -> BoardPos {
+        match self {
+            GhostKind::Blinky | GhostKind::Pinky => HOUSE_CENTER,
+            GhostKind::Inky => BoardPos { x: 11, y: 14 },
+            GhostKind::Clyde => BoardPos { x: 15, y: 14 },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GhostState {
+    InHouse,
+    LeavingHouse,
+    Active,
+    // just the eyes, heading back to the house
+    Eaten,
+    EnteringHouse,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GhostMode {
+    Scatter,
+    Chase,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    // ticks left before play starts
+    Ready(u32),
+    Playing,
+    Dying(u32),
+    LevelComplete(u32),
+    // a coffee break between levels
+    Intermission(Cutscene),
+    GameOver,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pacman {
+    pub pos: Vec2,
+    pub dir: Dir,
+    // the last direction the joystick was pushed, taken at the next chance
+    pub wanted_dir: Dir,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ghost {
+    pub kind: GhostKind,
+    pub pos: Vec2,
+    pub dir: Dir,
+    pub state: GhostState,
+    pub frightened: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BonusFruit {
+    pub fruit: Fruit,
+    pub ticks_left: u32,
+}
+
+#[derive(Clone)]
+pub struct Game {
+    pub config: Config,
+    pub players: Players,
+    pub pacman: Pacman,
+    pub ghosts: [Ghost; 4],
+    pub bonus_fruit: Option<BonusFruit>,
+    pub phase: Phase,
+    pub rng: Rng,
+    // ticks since the game started
+    pub ticks: u64,
+
+    pub mode: GhostMode,
+    // index into the level's scatter/chase schedule, and ticks spent in it
+    pub mode_index: usize,
+    pub mode_ticks: u32,
+    pub fright_ticks: u32,
+    // ghosts eaten on the current power pellet
+    pub ghosts_eaten: u32,
+
+    // house release counters, reset every life
+    pub house_pellets: u32,
+    pub house_idle_ticks: u32,
+    // after a death the ghosts are released on the global pellet counts
+    pub life_lost_this_level: bool,
+
+    // the maze each level starts with, shared by every copy of the game
+    pub maze: Arc<Board>,
+    // indexed by `GhostKind::index`, the arcade's unless replaced
+    pub ghost_ais: [Box<dyn GhostAi>; 4],
+
+    // keep every ghost decision and Pac-Man turn for `decisions`, off unless
+    // something is writing a trace
+    pub trace_decisions: bool,
+
+    // what happened during the last tick
+    events: Vec<GameEvent>,
+    // and what the actors chose, while `trace_decisions` is on
+    decisions: Vec<Decision>,
+}
+
+impl Game {
+    pub fn new(config: Config, num_players: usize, seed: u64) -> Game {
+        Game::with_maze(config, num_players, seed, Board::new())
+    }
+
+    // See `check_maze` for what a maze needs
+    pub fn with_maze(config: Config, num_players: usize, seed: u64, maze: Board) -> Game {
+        let mut first = Player::new(config.lives);
+        first.level = config.start_level;
+        first.board = maze.clone();
+        let mut game = Game {
+            config,
+            players: Players::new(num_players, first),
+            pacman: Pacman {
+                pos: tile_pos(PACMAN_START),
+                dir: Dir::Left,
+                wanted_dir: Dir::Left,
+            },
+            ghosts: [
+                Ghost::new(GhostKind::Blinky),
+                Ghost::new(GhostKind::Pinky),
+                Ghost::new(GhostKind::Inky),
+                Ghost::new(GhostKind::Clyde),
+            ],
+            bonus_fruit: None,
+            phase: Phase::Ready(READY_TICKS),
+            rng: Rng::new(seed),
+            ticks: 0,
+            mode: GhostMode::Scatter,
+            mode_index: 0,
+            mode_ticks: 0,
+            fright_ticks: 0,
+            ghosts_eaten: 0,
+            house_pellets: 0,
+            house_idle_ticks: 0,
+            life_lost_this_level: false,
+            maze: Arc::new(maze),
+            ghost_ais: [
+                ghost_ai::arcade(GhostKind::Blinky),
+                ghost_ai::arcade(GhostKind::Pinky),
+                ghost_ai::arcade(GhostKind::Inky),
+                ghost_ai::arcade(GhostKind::Clyde),
+            ],
+            trace_decisions: false,
+            events: Vec::new(),
+            decisions: Vec::new(),
+        };
+        game.reset_actors();
+        game
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.players.current().board
+    }
+
+    pub fn level_spec(&self) -> LevelSpec {
+        level_spec(self.players.current().level, self.config.difficulty)
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        self.phase == Phase::GameOver
+    }
+
+    pub fn pacman_tile(&self) -> BoardPos {
+        tile_of(self.board(), self.pacman.pos)
+    }
+
+    pub fn ghost_tile(&self, kind: GhostKind) -> BoardPos {
+        tile_of(self.board(), self.ghosts[kind.index()].pos)
+    }
+
+    pub fn set_ghost_ai(&mut self, kind: GhostKind, ai: Box<dyn GhostAi>) {
+        self.ghost_ais[kind.index()] = ai;
+    }
+
+    pub fn events(&self) -> &[GameEvent] {
+        &self.events
+    }
+
+    pub fn decisions(&self) -> &[Decision] {
+        &self.decisions
+    }
+
+    pub fn tick(&mut self, input: GameInput) {
+        self.ticks += 1;
+        self.events.clear();
+        self.decisions.clear();
+        match self.phase {
+            Phase::Ready(0) => self.phase = Phase::Playing,
+            Phase::Ready(t) => self.phase = Phase::Ready(t - 1),
+            Phase::Playing => self.tick_playing(input),
+            Phase::Dying(0) => self.finish_death(),
+            Phase::Dying(t) => self.phase = Phase::Dying(t - 1),
+            Phase::LevelComplete(0) => self.finish_level(),
+            Phase::LevelComplete(t) => self.phase = Phase::LevelComplete(t - 1),
+            Phase::Intermission(mut cutscene) => {
+                cutscene.tick();
+                self.phase = if cutscene.is_finished() {
+                    Phase::Ready(READY_TICKS)
+                } else {
+                    Phase::Intermission(cutscene)
+                };
+            }
+            Phase::GameOver => {}
+        }
+    }
+
+    pub fn skip_intermission(&mut self) {
+        if let Phase::Intermission(cutscene) = &mut self.phase {
+            cutscene.skip();
+        }
+    }
+
+    fn tick_playing(&mut self, input: GameInput) {
+        match input {
+            GameInput::Up => self.pacman.wanted_dir = Dir::Up,
+            GameInput::Left => self.pacman.wanted_dir = Dir::Left,
+            GameInput::Down => self.pacman.wanted_dir = Dir::Down,
+            GameInput::Right => self.pacman.wanted_dir = Dir::Right,
+            GameInput::Step | GameInput::Nil => {}
+        }
+
+        self.move_pacman();
+        self.eat();
+        if self.board().num_pellets_remaining() == 0 {
+            self.phase = Phase::LevelComplete(LEVEL_COMPLETE_TICKS);
+            let level = self.players.current().level;
+            self.events.push(GameEvent::LevelCleared { level });
+            return;
+        }
+        if self.check_collisions() {
+            return;
+        }
+
+        self.update_modes();
+        self.release_ghosts();
+        for i in 0..self.ghosts.len() {
+            self.move_ghost(i);
+        }
+        if self.check_collisions() {
+            return;
+        }
+
+        if let Some(fruit) = &mut self.bonus_fruit {
+            fruit.ticks_left -= 1;
+            if fruit.ticks_left == 0 {
+                self.bonus_fruit = None;
+            }
+        }
+    }
+
+    fn move_pacman(&mut self) {
+        let spec = self.level_spec();
+        let speed = MAX_SPEED.percent(if self.fright_ticks > 0 {
+            spec.pacman_fright_speed
+        } else {
+            spec.pacman_speed
+        });
+
+        let pacman = &mut self.pacman;
+        let board = &self.players.current().board;
+
+        let from = pacman.dir;
+        // Pac-Man can reverse anywhere, every other turn waits for a tile center
+        if pacman.wanted_dir == pacman.dir.opposite() {
+            pacman.dir = pacman.wanted_dir;
+        }
+        let wanted_dir = pacman.wanted_dir;
+        walk(
+            board,
+            &mut pacman.pos,
+            &mut pacman.dir,
+            speed,
+            pacman_can_enter,
+            |board, tile, dir| match board.neighbor(tile, wanted_dir) {
+                Some(next) if pacman_can_enter(board, next) => wanted_dir,
+                _ => dir,
+            },
+        );
+        if self.trace_decisions && pacman.dir != from {
+            let tile = tile_of(board, pacman.pos);
+            let to = pacman.dir;
+            self.decisions.push(Decision::PacmanTurn { tile, from, to });
+        }
+    }
+
+    fn eat(&mut self) {
+        let tile = self.pacman_tile();
+        let board = &mut self.players.current_mut().board;
+        let h = board.get_tile_of_board_pos(tile);
+
+        let points = if board.has_pellet[h] {
+            board.has_pellet[h] = false;
+            self.events.push(GameEvent::PelletEaten { tile });
+            PELLET_POINTS
+        } else if board.has_power_pellet[h] {
+            board.has_power_pellet[h] = false;
+            self.events.push(GameEvent::EnergizerEaten { tile });
+            self.frighten_ghosts();
+            POWER_PELLET_POINTS
+        } else {
+            0
+        };
+
+        if points > 0 {
+            self.add_score(points);
+            self.house_pellets += 1;
+            self.house_idle_ticks = 0;
+
+            let board = self.board();
+            let pellets_eaten = board.num_pellets_at_start - board.num_pellets_remaining();
+            if FRUIT_PELLETS.contains(&pellets_eaten) {
+                let fruit = self.level_spec().bonus_fruit;
+                self.bonus_fruit = Some(BonusFruit {
+                    fruit,
+                    ticks_left: FRUIT_TICKS,
+                });
+                self.events.push(GameEvent::FruitSpawned { fruit });
+            }
+        } else {
+            self.house_idle_ticks += 1;
+        }
+
+        if let Some(fruit) = self.bonus_fruit {
+            if tile == FRUIT_POS {
+                self.bonus_fruit = None;
+                let points = fruit.fruit.points();
+                self.events.push(GameEvent::FruitEaten {
+                    fruit: fruit.fruit,
+                    points,
+                });
+                self.add_score(points);
+            }
+        }
+    }
+
+    fn add_score(&mut self, points: u32) {
+        let bonus_life = self.config.bonus_life;
+        if self.players.current_mut().add_score(points, bonus_life) {
+            self.events.push(GameEvent::ExtraLife);
+        }
+    }
+
+    fn frighten_ghosts(&mut self) {
+        self.fright_ticks = self.level_spec().fright_secs * TICKS_PER_SECOND;
+        self.ghosts_eaten = 0;
+        let frightened = self.fright_ticks > 0;
+        for ghost in self.ghosts.iter_mut() {
+            // on the later levels ghosts still reverse, they just don't turn blue
+            if ghost.state == GhostState::Active {
+                ghost.dir = ghost.dir.opposite();
+                ghost.frightened = frightened;
+            }
+        }
+    }
+
+    // Returns true if Pac-Man died
+    fn check_collisions(&mut self) -> bool {
+        let pacman_tile = self.pacman_tile();
+        for i in 0..self.ghosts.len() {
+            if self.ghosts[i].state != GhostState::Active {
+                continue;
+            }
+            if tile_of(self.board(), self.ghosts[i].pos) != pacman_tile {
+                continue;
+            }
+            if self.ghosts[i].frightened {
+                self.ghosts[i].state = GhostState::Eaten;
+                self.ghosts[i].frightened = false;
+                let points = FIRST_GHOST_POINTS << self.ghosts_eaten.min(3);
+                self.ghosts_eaten += 1;
+                self.events.push(GameEvent::GhostEaten {
+                    ghost: self.ghosts[i].kind,
+                    points,
+                });
+                self.add_score(points);
+            } else {
+                self.phase = Phase::Dying(DYING_TICKS);
+                self.events.push(GameEvent::PacmanDied {
+                    ghost: self.ghosts[i].kind,
+                });
+                return true;
+            }
+        }
+        false
+    }
+
+    fn update_modes(&mut self) {
+        // the scatter/chase timer is paused while the ghosts are frightened
+        if self.fright_ticks > 0 {
+            self.fright_ticks -= 1;
+            if self.fright_ticks == 0 {
+                for ghost in self.ghosts.iter_mut() {
+                    ghost.frightened = false;
+                }
+                self.events.push(GameEvent::FrightEnded);
+            }
+            return;
+        }
+
+        let schedule = mode_schedule(self.players.current().level);
+        if self.mode_index >= schedule.len() {
+            // chase forever
+            return;
+        }
+        self.mode_ticks += 1;
+        if self.mode_ticks >= schedule[self.mode_index] {
+            self.mode_ticks = 0;
+            self.mode_index += 1;
+            self.mode = if self.mode_index.is_multiple_of(2) {
+                GhostMode::Scatter
+            } else {
+                GhostMode::Chase
+            };
+            self.events.push(GameEvent::ModeChanged { mode: self.mode });
+            // every mode change makes the ghosts reverse
+            for ghost in self.ghosts.iter_mut() {
+                if ghost.state == GhostState::Active {
+                    ghost.dir = ghost.dir.opposite();
+                }
+            }
+        }
+    }
+
+    // A simplified version of the Dossier's dot counters: one counter of pellets
+    // eaten this life and fixed limits per ghost.
+    fn release_ghosts(&mut self) {
+        let level = self.players.current().level;
+        let limit = |kind: GhostKind| -> u32 {
+            if self.life_lost_this_level {
+                return match kind {
+                    GhostKind::Blinky => 0,
+                    GhostKind::Pinky => 7,
+                    GhostKind::Inky => 17,
+                    GhostKind::Clyde => 32,
+                };
+            }
+            match (kind, level) {
+                (GhostKind::Inky, 1) => 30,
+                (GhostKind::Clyde, 1) => 60,
+                (GhostKind::Clyde, 2) => 50,
+                _ => 0,
+            }
+        };
+
+        // only the first ghost still in the house is considered
+        let waiting = self
+            .ghosts
+            .iter()
+            .position(|g| g.state == GhostState::InHouse);
+        if let Some(i) = waiting {
+            let kind = self.ghosts[i].kind;
+            if self.house_pellets >= limit(kind) {
+                self.ghosts[i].state = GhostState::LeavingHouse;
+            } else if self.house_idle_ticks >= HOUSE_IDLE_RELEASE_TICKS {
+                self.ghosts[i].state = GhostState::LeavingHouse;
+                self.house_idle_ticks = 0;
+            }
+        }
+    }
+
+    fn move_ghost(&mut self, i: usize) {
+        let spec = self.level_spec();
+        let level = self.players.current().level;
+        let pacman_tile = self.pacman_tile();
+        let ghost_tiles = self.ghost_tiles();
+        let mode = self.mode;
+        let pacman = &self.pacman;
+        let board = &self.players.current().board;
+        let rng = &mut self.rng;
+        let ai = &mut self.ghost_ais[i];
+        let ghost = &mut self.ghosts[i];
+        let tracing = self.trace_decisions;
+        let decisions = &mut self.decisions;
+        let kind = ghost.kind;
+        // only real choices, where more than one way is open
+        let mut note = |board: &Board, tile, dir, mode, target, chosen| {
+            if !tracing {
+                return;
+            }
+            let options = ghost_options(board, tile, dir);
+            if options.len() > 1 {
+                decisions.push(Decision::Ghost(GhostDecision {
+                    ghost: kind,
+                    tile,
+                    heading: dir,
+                    mode,
+                    target,
+                    options: trace::with_distances(board, tile, &options, target),
+                    chosen,
+                }));
+            }
+        };
+
+        match ghost.state {
+            GhostState::InHouse => {}
+            GhostState::LeavingHouse => {
+                if move_toward(&mut ghost.pos, tile_pos(HOUSE_EXIT), HOUSE_SPEED) {
+                    ghost.state = GhostState::Active;
+                    ghost.dir = Dir::Left;
+                }
+            }
+            GhostState::EnteringHouse => {
+                if move_toward(&mut ghost.pos, tile_pos(HOUSE_CENTER), EYES_SPEED) {
+                    ghost.state = GhostState::LeavingHouse;
+                }
+            }
+            GhostState::Eaten => {
+                let exit = HOUSE_EXIT;
+                walk(
+                    board,
+                    &mut ghost.pos,
+                    &mut ghost.dir,
+                    EYES_SPEED,
+                    ghost_can_enter,
+                    |board, tile, dir| {
+                        let options = ghost_options(board, tile, dir);
+                        if options.is_empty() {
+                            // dead end, there are none in this maze
+                            return dir.opposite();
+                        }
+                        let view = GhostView {
+                            board,
+                            kind,
+                            tile,
+                            dir,
+                            options: &options,
+                            mode,
+                            level,
+                            pacman,
+                            pacman_tile,
+                            ghost_tiles,
+                        };
+                        let target = (exit.x as i32, exit.y as i32);
+                        let chosen = ghost_ai::toward(&view, target);
+                        note(board, tile, dir, TraceMode::Eyes, Some(target), chosen);
+                        chosen
+                    },
+                );
+                if tile_of(board, ghost.pos) == HOUSE_EXIT {
+                    ghost.pos = tile_pos(HOUSE_EXIT);
+                    ghost.state = GhostState::EnteringHouse;
+                }
+            }
+            GhostState::Active => {
+                let h = board.get_tile_of_board_pos(tile_of(board, ghost.pos));
+                let speed = MAX_SPEED.percent(if board.tile_is_tunnel(h) {
+                    spec.ghost_tunnel_speed
+                } else if ghost.frightened {
+                    spec.ghost_fright_speed
+                } else {
+                    spec.ghost_speed
+                });
+                let frightened = ghost.frightened;
+                walk(
+                    board,
+                    &mut ghost.pos,
+                    &mut ghost.dir,
+                    speed,
+                    ghost_can_enter,
+                    |board, tile, dir| {
+                        // frightened ghosts wander at random
+                        if frightened {
+                            let chosen = choose_dir(board, tile, dir, rng);
+                            note(board, tile, dir, TraceMode::Frightened, None, chosen);
+                            return chosen;
+                        }
+                        let options = ghost_options(board, tile, dir);
+                        if options.len() < 2 {
+                            return options.first().cloned().unwrap_or(dir.opposite());
+                        }
+                        let view = GhostView {
+                            board,
+                            kind,
+                            tile,
+                            dir,
+                            options: &options,
+                            mode,
+                            level,
+                            pacman,
+                            pacman_tile,
+                            ghost_tiles,
+                        };
+                        let chosen = ai.choose_dir(&view);
+                        let chosen = if options.contains(&chosen) {
+                            chosen
+                        } else {
+                            options[0]
+                        };
+                        if tracing {
+                            let target = ai.target(&view);
+                            note(board, tile, dir, TraceMode::from_mode(mode), target, chosen);
+                        }
+                        chosen
+                    },
+                );
+            }
+        }
+    }
+
+    pub fn ghost_tiles(&self) -> [BoardPos; 4] {
+        [
+            self.ghost_tile(GhostKind::Blinky),
+            self.ghost_tile(GhostKind::Pinky),
+            self.ghost_tile(GhostKind::Inky),
+            self.ghost_tile(GhostKind::Clyde),
+        ]
+    }
+
+    // The tile the ghost's AI is heading for right now, if it uses one
+    pub fn ghost_target(&self, kind: GhostKind) -> Option<(i32, i32)> {
+        let ghost = &self.ghosts[kind.index()];
+        let view = GhostView {
+            board: self.board(),
+            kind,
+            tile: self.ghost_tile(kind),
+            dir: ghost.dir,
+            options: &[],
+            mode: self.mode,
+            level: self.players.current().level,
+            pacman: &self.pacman,
+            pacman_tile: self.pacman_tile(),
+            ghost_tiles: self.ghost_tiles(),
+        };
+        self.ghost_ais[kind.index()].target(&view)
+    }
+
+    // Debug tools. These change the game in ways no input could, so replays
+    // and recordings of a game that used them won't play back.
+
+    pub fn teleport_pacman(&mut self, tile: BoardPos) -> Result<(), String> {
+        if !self.board().contains(tile) || !pacman_can_enter(self.board(), tile) {
+            return Err(format!("Pac-Man can't stand on ({}, {})", tile.x, tile.y));
+        }
+        self.pacman.pos = tile_pos(tile);
+        Ok(())
+    }
+
+    // A ghost put anywhere is out and about, even if it was in the house or
+    // just eyes
+    pub fn teleport_ghost(&mut self, kind: GhostKind, tile: BoardPos) -> Result<(), String> {
+        let board = self.board();
+        if !board.contains(tile) || !board.tile_is_traversable(board.get_tile_of_board_pos(tile)) {
+            return Err(format!(
+                "{:?} can't stand on ({}, {})",
+                kind, tile.x, tile.y
+            ));
+        }
+        let ghost = &mut self.ghosts[kind.index()];
+        ghost.pos = tile_pos(tile);
+        ghost.state = GhostState::Active;
+        Ok(())
+    }
+
+    // Switch to `mode` until the schedule's next change, reversing the ghosts
+    // like a scheduled change would
+    pub fn set_mode(&mut self, mode: GhostMode) {
+        if mode == self.mode {
+            return;
+        }
+        self.mode = mode;
+        for ghost in self.ghosts.iter_mut() {
+            if ghost.state == GhostState::Active {
+                ghost.dir = ghost.dir.opposite();
+            }
+        }
+    }
+
+    // As if Pac-Man had eaten a power pellet, without the points
+    pub fn frighten(&mut self) {
+        self.frighten_ghosts();
+    }
+
+    fn finish_death(&mut self) {
+        self.players.on_death();
+        if self.players.is_game_over() {
+            self.phase = Phase::GameOver;
+            return;
+        }
+        self.life_lost_this_level = true;
+        self.reset_actors();
+    }
+
+    fn finish_level(&mut self) {
+        let cleared_level = self.players.current().level;
+        let maze = self.maze.clone();
+        self.players.current_mut().advance_level(&maze);
+        self.life_lost_this_level = false;
+        self.reset_actors();
+        if let Some(intermission) = Intermission::after_level(cleared_level) {
+            self.phase = Phase::Intermission(Cutscene::new(intermission));
+        }
+    }
+
+    // Start of a life, or of a level
+    fn reset_actors(&mut self) {
+        self.pacman = Pacman {
+            pos: tile_pos(PACMAN_START),
+            dir: Dir::Left,
+            wanted_dir: Dir::Left,
+        };
+        for ghost in self.ghosts.iter_mut() {
+            *ghost = Ghost::new(ghost.kind);
+        }
+        self.bonus_fruit = None;
+        self.mode = GhostMode::Scatter;
+        self.mode_index = 0;
+        self.mode_ticks = 0;
+        self.fright_ticks = 0;
+        self.ghosts_eaten = 0;
+        self.house_pellets = 0;
+        self.house_idle_ticks = 0;
+        self.phase = Phase::Ready(READY_TICKS);
+    }
+}
+
+impl Ghost {
+    pub fn new(kind: GhostKind) -> Ghost {
+        // Blinky starts outside the house
+        let (pos, state) = match kind {
+            GhostKind::Blinky => (tile_pos(HOUSE_EXIT), GhostState::Active),
+            _ => (tile_pos(kind.home()), GhostState::InHouse),
+        };
+        Ghost {
+            kind,
+            pos,
+            dir: Dir::Left,
+            state,
+            frightened: false,
+        }
+    }
+}
+
+// Durations of alternating scatter, chase, scatter, ... phases.
+// After the last one the ghosts chase indefinitely.
+fn mode_schedule(level: u32) -> [u32; 7] {
+    let s = TICKS_PER_SECOND;
+    match level {
+        1 => [7 * s, 20 * s, 7 * s, 20 * s, 5 * s, 20 * s, 5 * s],
+        2..=4 => [7 * s, 20 * s, 7 * s, 20 * s, 5 * s, 1033 * s, 1],
+        _ => [5 * s, 20 * s, 5 * s, 20 * s, 5 * s, 1037 * s, 1],
+    }
+}
+
+// A maze from a file has to leave room for the actors where the arcade puts them
+pub fn check_maze(maze: &Board) -> Result<(), String> {
+    let mut needed = vec![
+        ("Pac-Man's start", PACMAN_START),
+        ("the house exit", HOUSE_EXIT),
+    ];
+    for kind in GhostKind::ALL.iter() {
+        needed.push(("a ghost's start", kind.home()));
+    }
+    for (what, pos) in needed {
+        if !maze.tile_is_traversable(maze.get_tile_of_board_pos(pos)) {
+            return Err(format!("{} at ({}, {}) is a wall", what, pos.x, pos.y));
+        }
+    }
+    Ok(())
+}
+
+pub fn tile_pos(board_pos: BoardPos) -> Vec2 {
+    Vec2::from_pixels(
+        board_pos.x as i32 * Board::TILE_WIDTH,
+        board_pos.y as i32 * Board::TILE_WIDTH,
+    )
+}
+
+// The tile under the actor's center, wrapping through the tunnel
+pub fn tile_of(board: &Board, pos: Vec2) -> BoardPos {
+    let half = Fixed::pixels(Board::TILE_WIDTH / 2);
+    let x = (pos.x + half).div_euclid(TILE);
+    let y = (pos.y + half).div_euclid(TILE);
+    BoardPos {
+        x: x.rem_euclid(board.width as i32) as usize,
+        y: y.clamp(0, board.height as i32 - 1) as usize,
+    }
+}
+
+pub fn pacman_can_enter(board: &Board, tile: BoardPos) -> bool {
+    let h = board.get_tile_of_board_pos(tile);
+    board.tile_is_traversable(h) && !board.tile_is_ghost_door(h)
+}
+
+// outside the house the ghosts treat the door like a wall
+fn ghost_can_enter(board: &Board, tile: BoardPos) -> bool {
+    pacman_can_enter(board, tile)
+}
+
+fn is_centered(pos: Vec2) -> bool {
+    pos.x.rem_euclid(TILE) == Fixed::ZERO && pos.y.rem_euclid(TILE) == Fixed::ZERO
+}
+
+// Distance along `dir` to the next tile center, a full tile if already on one
+fn dist_to_center(pos: Vec2, dir: Dir) -> Fixed {
+    let (coord, forward) = match dir {
+        Dir::Up => (pos.y, false),
+        Dir::Left => (pos.x, false),
+        Dir::Down => (pos.y, true),
+        Dir::Right => (pos.x, true),
+    };
+    let rem = coord.rem_euclid(TILE);
+    if rem == Fixed::ZERO {
+        TILE
+    } else if forward {
+        TILE - rem
+    } else {
+        rem
+    }
+}
+
+// Move `dist` pixels through the maze. At every tile center `decide` picks the
+// direction to leave in; if that tile can't be entered the actor stops there.
+fn walk(
+    board: &Board,
+    pos: &mut Vec2,
+    dir: &mut Dir,
+    mut dist: Fixed,
+    can_enter: impl Fn(&Board, BoardPos) -> bool,
+    mut decide: impl FnMut(&Board, BoardPos, Dir) -> Dir,
+) {
+    let board_width = TILE * board.width as i32;
+    while dist > Fixed::ZERO {
+        if is_centered(*pos) {
+            let tile = tile_of(board, *pos);
+            *dir = decide(board, tile, *dir);
+            match board.neighbor(tile, *dir) {
+                Some(next) if can_enter(board, next) => {}
+                _ => return,
+            }
+        }
+
+        let (dx, dy) = dir.offset();
+        let to_center = dist_to_center(*pos, *dir);
+        if dist < to_center {
+            pos.x += dist * dx;
+            pos.y += dist * dy;
+            return;
+        }
+
+        pos.x += to_center * dx;
+        pos.y += to_center * dy;
+        if pos.x < Fixed::ZERO {
+            pos.x += board_width;
+        } else if pos.x >= board_width {
+            pos.x -= board_width;
+        }
+        dist -= to_center;
+    }
+}
+
+// Straight line movement that ignores walls, used in and around the ghost house.
+// Lines up horizontally first. Returns true on arrival.
+fn move_toward(pos: &mut Vec2, target: Vec2, mut dist: Fixed) -> bool {
+    let dx = target.x - pos.x;
+    let step = dx.abs().min(dist);
+    pos.x += step * dx.signum();
+    dist -= step;
+
+    let dy = target.y - pos.y;
+    let step = dy.abs().min(dist);
+    pos.y += step * dy.signum();
+
+    *pos == target
+}
+
+// Where a ghost on `tile` heading `dir` may go: not back, not into walls
+fn ghost_options(board: &Board, tile: BoardPos, dir: Dir) -> Vec<Dir> {
+    Dir::ALL
+        .iter()
+        .cloned()
+        .filter(|&d| d != dir.opposite())
+        .filter(|&d| match board.neighbor(tile, d) {
+            Some(next) => ghost_can_enter(board, next),
+            None => false,
+        })
+        .collect()
+}
+
+// For frightened ghosts, which have no target
+fn choose_dir(board: &Board, tile: BoardPos, dir: Dir, rng: &mut Rng) -> Dir {
+    let candidates = ghost_options(board, tile, dir);
+    if candidates.is_empty() {
+        // dead end, there are none in this maze
+        return dir.opposite();
+    }
+
+    // a random direction if it's open, otherwise the first open one
+    let random_dir = Dir::ALL[rng.below(4) as usize];
+    if candidates.contains(&random_dir) {
+        random_dir
+    } else {
+        candidates[0]
+    }
+}
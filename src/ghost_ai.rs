@@ -0,0 +1,166 @@
+// How ghosts pick their way through the maze.
+//
+// A ghost only gets a choice on a tile center where more than one way is open,
+// and never back the way it came. That choice goes through `GhostAi`; the
+// arcade's four personalities below are the default ones. Frightened ghosts
+// and eyes heading home are handled by the game itself, like the arcade.
+//
+// AIs get cloned along with the game (rewind, batch runs) so anything they
+// remember has to live in the AI itself, and to keep games deterministic they
+// shouldn't use randomness or timing of their own.
+use crate::board::{Board, BoardPos, Dir};
+use crate::game::{GhostKind, GhostMode, Pacman};
+
+// Everything a ghost can see when it has to decide
+pub struct GhostView<'a> {
+    pub board: &'a Board,
+    pub kind: GhostKind,
+    // the tile being decided on and the way the ghost is heading
+    pub tile: BoardPos,
+    pub dir: Dir,
+    // the directions it may take, in `Dir::ALL` order
+    pub options: &'a [Dir],
+    pub mode: GhostMode,
+    pub level: u32,
+    pub pacman: &'a Pacman,
+    pub pacman_tile: BoardPos,
+    // indexed by `GhostKind::index`
+    pub ghost_tiles: [BoardPos; 4],
+}
+
+pub trait GhostAi: Send {
+    // Must be one of `view.options`, anything else is ignored
+    fn choose_dir(&mut self, view: &GhostView) -> Dir;
+
+    // The tile the ghost is heading for, for AIs that think that way.
+    // Only used to show what the AI is doing.
+    fn target(&self, _view: &GhostView) -> Option<(i32, i32)> {
+        None
+    }
+
+    fn name(&self) -> &str;
+
+    fn clone_box(&self) -> Box<dyn GhostAi>;
+}
+
+impl Clone for Box<dyn GhostAi> {
+    fn clone(&self) -> Box<dyn GhostAi> {
+        self.clone_box()
+    }
+}
+
+// The arcade's AI for `kind`
+pub fn arcade(kind: GhostKind) -> Box<dyn GhostAi> {
+    match kind {
+        GhostKind::Blinky => Box::new(Shadow),
+        GhostKind::Pinky => Box::new(Speedy),
+        GhostKind::Inky => Box::new(Bashful),
+        GhostKind::Clyde => Box::new(Pokey),
+    }
+}
+
+// The option that gets closest to `target` in a straight line, ties going to
+// the earlier direction in `Dir::ALL`
+pub fn toward(view: &GhostView, (target_x, target_y): (i32, i32)) -> Dir {
+    let mut best = view.options[0];
+    let mut best_dist = i32::MAX;
+    for &d in view.options.iter() {
+        let next = view.board.neighbor(view.tile, d).unwrap();
+        let dx = next.x as i32 - target_x;
+        let dy = next.y as i32 - target_y;
+        let dist = dx * dx + dy * dy;
+        // strictly less, so ties go to the earlier direction
+        if dist < best_dist {
+            best = d;
+            best_dist = dist;
+        }
+    }
+    best
+}
+
+// `n` tiles ahead of Pac-Man, including the overflow bug that also shifts the
+// target left when Pac-Man faces up
+fn ahead_of_pacman(view: &GhostView, n: i32) -> (i32, i32) {
+    let (dx, dy) = view.pacman.dir.offset();
+    let bug = if view.pacman.dir == Dir::Up { -n } else { 0 };
+    (
+        view.pacman_tile.x as i32 + dx * n + bug,
+        view.pacman_tile.y as i32 + dy * n,
+    )
+}
+
+fn pacman_tile(view: &GhostView) -> (i32, i32) {
+    (view.pacman_tile.x as i32, view.pacman_tile.y as i32)
+}
+
+// Blinky: straight for Pac-Man
+#[derive(Clone)]
+pub struct Shadow;
+
+// Pinky: four tiles ahead of Pac-Man
+#[derive(Clone)]
+pub struct Speedy;
+
+// Inky: Blinky's position mirrored around the tile two ahead of Pac-Man
+#[derive(Clone)]
+pub struct Bashful;
+
+// Clyde: like Blinky until within 8 tiles of Pac-Man, then back to the corner
+#[derive(Clone)]
+pub struct Pokey;
+
+// Where `personality` would go if it were the ghost in `view`. Scatter corners
+// belong to the ghost rather than the personality.
+fn arcade_target(personality: GhostKind, view: &GhostView) -> (i32, i32) {
+    if view.mode == GhostMode::Scatter {
+        return view.kind.scatter_target();
+    }
+    match personality {
+        GhostKind::Blinky => pacman_tile(view),
+        GhostKind::Pinky => ahead_of_pacman(view, 4),
+        GhostKind::Inky => {
+            let (x, y) = ahead_of_pacman(view, 2);
+            let blinky = view.ghost_tiles[GhostKind::Blinky.index()];
+            (2 * x - blinky.x as i32, 2 * y - blinky.y as i32)
+        }
+        GhostKind::Clyde => {
+            let (pac_x, pac_y) = pacman_tile(view);
+            let me = view.ghost_tiles[view.kind.index()];
+            let dx = me.x as i32 - pac_x;
+            let dy = me.y as i32 - pac_y;
+            if dx * dx + dy * dy > 8 * 8 {
+                (pac_x, pac_y)
+            } else {
+                view.kind.scatter_target()
+            }
+        }
+    }
+}
+
+// The personalities only differ in their targets
+macro_rules! arcade_ai {
+    ($ai:ident, $personality:expr, $name:expr) => {
+        impl GhostAi for $ai {
+            fn choose_dir(&mut self, view: &GhostView) -> Dir {
+                toward(view, arcade_target($personality, view))
+            }
+
+            fn target(&self, view: &GhostView) -> Option<(i32, i32)> {
+                Some(arcade_target($personality, view))
+            }
+
+            fn name(&self) -> &str {
+                $name
+            }
+
+            fn clone_box(&self) -> Box<dyn GhostAi> {
+                Box::new(self.clone())
+            }
+        }
+    };
+}
+
+arcade_ai!(Shadow, GhostKind::Blinky, "shadow");
+arcade_ai!(Speedy, GhostKind::Pinky, "speedy");
+arcade_ai!(Bashful, GhostKind::Inky, "bashful");
+arcade_ai!(Pokey, GhostKind::Clyde, "pokey");
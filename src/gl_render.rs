@@ -155,3 +155,107 @@ fn cstring_from_len(len: usize) -> CString {
     buffer[len] = 0;
     unsafe { CString::from_vec_unchecked(buffer) }
 }
+
+// Solid rectangles in screen pixels, 0,0 top left, drawn in one go. Uses a
+// program whose `layout (location = 0)` is a vec2 position and
+// `layout (location = 1)` a vec3 color.
+pub struct RectBatch {
+    vao_handle: gl::types::GLuint,
+    vbo_handle: gl::types::GLuint,
+    screen_width: f32,
+    screen_height: f32,
+    // x, y, r, g, b for each vertex, two triangles per rectangle
+    vertices: Vec<f32>,
+}
+
+const FLOATS_PER_VERTEX: usize = 5;
+
+impl RectBatch {
+    pub fn new(screen_width: u32, screen_height: u32) -> RectBatch {
+        let mut vbo_handle: gl::types::GLuint = 0;
+        let mut vao_handle: gl::types::GLuint = 0;
+        let stride = (FLOATS_PER_VERTEX * std::mem::size_of::<f32>()) as gl::types::GLint;
+        unsafe {
+            gl::GenBuffers(1, &mut vbo_handle);
+            gl::GenVertexArrays(1, &mut vao_handle);
+            gl::BindVertexArray(vao_handle);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo_handle);
+            gl::EnableVertexAttribArray(0);
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
+            gl::EnableVertexAttribArray(1);
+            gl::VertexAttribPointer(
+                1,
+                3,
+                gl::FLOAT,
+                gl::FALSE,
+                stride,
+                (2 * std::mem::size_of::<f32>()) as *const gl::types::GLvoid,
+            );
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            gl::BindVertexArray(0);
+        }
+        RectBatch {
+            vao_handle,
+            vbo_handle,
+            screen_width: screen_width as f32,
+            screen_height: screen_height as f32,
+            vertices: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, x: i32, y: i32, width: u32, height: u32, color: (u8, u8, u8)) {
+        // pixels to normalized device coordinates, y going up
+        let left = x as f32 / self.screen_width * 2.0 - 1.0;
+        let right = (x as f32 + width as f32) / self.screen_width * 2.0 - 1.0;
+        let top = 1.0 - y as f32 / self.screen_height * 2.0;
+        let bottom = 1.0 - (y as f32 + height as f32) / self.screen_height * 2.0;
+        let (r, g, b) = (
+            color.0 as f32 / 255.0,
+            color.1 as f32 / 255.0,
+            color.2 as f32 / 255.0,
+        );
+        for &(x, y) in [
+            (left, top),
+            (right, top),
+            (left, bottom),
+            (right, top),
+            (right, bottom),
+            (left, bottom),
+        ]
+        .iter()
+        {
+            self.vertices.extend_from_slice(&[x, y, r, g, b]);
+        }
+    }
+
+    // Draw everything pushed since the last call, then forget it
+    pub fn draw(&mut self) {
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo_handle);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (self.vertices.len() * std::mem::size_of::<f32>()) as gl::types::GLsizeiptr,
+                self.vertices.as_ptr() as *const gl::types::GLvoid,
+                gl::STREAM_DRAW,
+            );
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            gl::BindVertexArray(self.vao_handle);
+            gl::DrawArrays(
+                gl::TRIANGLES,
+                0,
+                (self.vertices.len() / FLOATS_PER_VERTEX) as gl::types::GLsizei,
+            );
+            gl::BindVertexArray(0);
+        }
+        self.vertices.clear();
+    }
+}
+
+impl Drop for RectBatch {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.vao_handle);
+            gl::DeleteBuffers(1, &self.vbo_handle);
+        }
+    }
+}
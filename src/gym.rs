@@ -0,0 +1,349 @@
+// A reinforcement learning environment over the game core, in the style of
+// OpenAI Gym: `reset(seed)` starts a game, `step(action)` plays it forward and
+// says how well that went.
+//
+// Observations are the maze as a stack of tile grids, one per channel, so
+// they can go straight into a convolutional network. Rewards come from the
+// game's events and are shaped with `Rewards`. Each step repeats the action
+// for `frame_skip` ticks, and ticks where the joystick does nothing (READY!,
+// dying, intermissions) are played through so every step is a real decision.
+use crate::board::{Board, BoardPos};
+use crate::config::Config;
+use crate::event::GameEvent;
+use crate::game::{Game, GhostState, Phase, FRUIT_POS};
+use crate::input::GameInput;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Nothing,
+    Up,
+    Left,
+    Down,
+    Right,
+}
+
+impl Action {
+    pub const ALL: [Action; 5] = [
+        Action::Nothing,
+        Action::Up,
+        Action::Left,
+        Action::Down,
+        Action::Right,
+    ];
+
+    // for agents that pick actions by number
+    pub fn from_index(i: usize) -> Option<Action> {
+        Action::ALL.get(i).cloned()
+    }
+
+    pub fn to_input(self) -> GameInput {
+        match self {
+            Action::Nothing => GameInput::Nil,
+            Action::Up => GameInput::Up,
+            Action::Left => GameInput::Left,
+            Action::Down => GameInput::Down,
+            Action::Right => GameInput::Right,
+        }
+    }
+}
+
+// The planes of an observation, in order
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Channel {
+    Wall,
+    Pellet,
+    PowerPellet,
+    Pacman,
+    Blinky,
+    Pinky,
+    Inky,
+    Clyde,
+    // any ghost that can be eaten
+    Frightened,
+    // eaten ghosts on their way home, harmless
+    Eyes,
+    Fruit,
+}
+
+impl Channel {
+    pub const ALL: [Channel; 11] = [
+        Channel::Wall,
+        Channel::Pellet,
+        Channel::PowerPellet,
+        Channel::Pacman,
+        Channel::Blinky,
+        Channel::Pinky,
+        Channel::Inky,
+        Channel::Clyde,
+        Channel::Frightened,
+        Channel::Eyes,
+        Channel::Fruit,
+    ];
+}
+
+pub const NUM_CHANNELS: usize = 11;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Observation {
+    pub width: usize,
+    pub height: usize,
+    // 0 or 1 per tile, laid out [channel][y][x]
+    pub grid: Vec<u8>,
+    pub score: u32,
+    pub lives: u32,
+    pub level: u32,
+}
+
+impl Observation {
+    pub fn from_game(game: &Game) -> Observation {
+        let board = game.board();
+        let (width, height) = (board.width, board.height);
+        let mut obs = Observation {
+            width,
+            height,
+            grid: vec![0; NUM_CHANNELS * width * height],
+            score: game.players.current().score,
+            lives: game.players.current().lives,
+            level: game.players.current().level,
+        };
+
+        for h in 0..board.num_tiles {
+            let pos = board.get_board_pos_of_tile(h);
+            if !board.tile_is_traversable(h) {
+                obs.set(Channel::Wall, pos);
+            }
+            if board.tile_has_pellet(h) {
+                obs.set(Channel::Pellet, pos);
+            }
+            if board.tile_has_power_pellet(h) {
+                obs.set(Channel::PowerPellet, pos);
+            }
+        }
+
+        obs.set(Channel::Pacman, game.pacman_tile());
+        for ghost in game.ghosts.iter() {
+            let channel =
+                if ghost.state == GhostState::Eaten || ghost.state == GhostState::EnteringHouse {
+                    Channel::Eyes
+                } else if ghost.frightened {
+                    Channel::Frightened
+                } else {
+                    Channel::ALL[Channel::Blinky as usize + ghost.kind.index()]
+                };
+            obs.set(channel, game.ghost_tile(ghost.kind));
+        }
+        if game.bonus_fruit.is_some() {
+            obs.set(Channel::Fruit, FRUIT_POS);
+        }
+        obs
+    }
+
+    pub fn get(&self, channel: Channel, pos: BoardPos) -> bool {
+        self.grid[self.index(channel, pos)] != 0
+    }
+
+    // One channel's plane, row by row
+    pub fn channel(&self, channel: Channel) -> &[u8] {
+        let size = self.width * self.height;
+        let start = channel as usize * size;
+        &self.grid[start..start + size]
+    }
+
+    fn set(&mut self, channel: Channel, pos: BoardPos) {
+        let i = self.index(channel, pos);
+        self.grid[i] = 1;
+    }
+
+    fn index(&self, channel: Channel, pos: BoardPos) -> usize {
+        (channel as usize * self.height + pos.y) * self.width + pos.x
+    }
+}
+
+// What each event is worth to the agent
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rewards {
+    pub pellet: f32,
+    pub power_pellet: f32,
+    // times the points the ghost was worth, so later ghosts are worth more
+    pub ghost_per_point: f32,
+    pub fruit_per_point: f32,
+    pub death: f32,
+    pub level_cleared: f32,
+    // every tick, negative to hurry the agent along
+    pub tick: f32,
+}
+
+impl Default for Rewards {
+    fn default() -> Rewards {
+        Rewards {
+            pellet: 1.0,
+            power_pellet: 5.0,
+            ghost_per_point: 0.05,
+            fruit_per_point: 0.01,
+            death: -50.0,
+            level_cleared: 100.0,
+            tick: 0.0,
+        }
+    }
+}
+
+impl Rewards {
+    fn for_event(&self, event: &GameEvent) -> f32 {
+        match event {
+            GameEvent::PelletEaten { .. } => self.pellet,
+            GameEvent::EnergizerEaten { .. } => self.power_pellet,
+            GameEvent::GhostEaten { points, .. } => self.ghost_per_point * *points as f32,
+            GameEvent::FruitEaten { points, .. } => self.fruit_per_point * *points as f32,
+            GameEvent::PacmanDied { .. } => self.death,
+            GameEvent::LevelCleared { .. } => self.level_cleared,
+            _ => 0.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EnvConfig {
+    pub game: Config,
+    pub rewards: Rewards,
+    // ticks each action is held for
+    pub frame_skip: u32,
+    // end the episode after this many steps, even if the game isn't over
+    pub max_steps: Option<u64>,
+}
+
+impl Default for EnvConfig {
+    fn default() -> EnvConfig {
+        EnvConfig {
+            game: Config::default(),
+            rewards: Rewards::default(),
+            frame_skip: 4,
+            max_steps: None,
+        }
+    }
+}
+
+pub struct Env {
+    pub config: EnvConfig,
+    game: Game,
+    steps: u64,
+}
+
+impl Env {
+    pub fn new(config: EnvConfig) -> Env {
+        assert!(config.frame_skip > 0);
+        let mut env = Env {
+            config,
+            game: Game::new(config.game, 1, 0),
+            steps: 0,
+        };
+        env.reset(0);
+        env
+    }
+
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.game = Game::new(self.config.game, 1, seed);
+        self.steps = 0;
+        self.skip_uncontrolled(&mut 0.0);
+        Observation::from_game(&self.game)
+    }
+
+    // (observation, reward, done)
+    pub fn step(&mut self, action: Action) -> (Observation, f32, bool) {
+        let mut reward = 0.0;
+        for _ in 0..self.config.frame_skip {
+            if self.game.is_game_over() {
+                break;
+            }
+            self.tick(action.to_input(), &mut reward);
+        }
+        self.skip_uncontrolled(&mut reward);
+        self.steps += 1;
+
+        let out_of_steps = self.config.max_steps.is_some_and(|max| self.steps >= max);
+        let done = self.game.is_game_over() || out_of_steps;
+        (Observation::from_game(&self.game), reward, done)
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    pub fn board(&self) -> &Board {
+        self.game.board()
+    }
+
+    fn tick(&mut self, input: GameInput, reward: &mut f32) {
+        self.game.tick(input);
+        *reward += self.config.rewards.tick;
+        for event in self.game.events() {
+            *reward += self.config.rewards.for_event(event);
+        }
+    }
+
+    // Play through the ticks where input does nothing
+    fn skip_uncontrolled(&mut self, reward: &mut f32) {
+        while self.game.phase != Phase::Playing && !self.game.is_game_over() {
+            self.game.skip_intermission();
+            self.tick(GameInput::Nil, reward);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Action, Channel, Env, EnvConfig, Rewards};
+    use crate::game::Phase;
+
+    #[test]
+    fn reset_starts_the_same_game_ready_to_play() {
+        let mut env = Env::new(EnvConfig::default());
+        let first = env.reset(3);
+        assert_eq!(env.game().phase, Phase::Playing);
+        assert_eq!((first.score, first.lives, first.level), (0, 3, 1));
+        assert_eq!(first.channel(Channel::Pacman).iter().sum::<u8>(), 1);
+        let pellets = first
+            .channel(Channel::Pellet)
+            .iter()
+            .chain(first.channel(Channel::PowerPellet));
+        assert_eq!(
+            pellets.map(|&p| p as usize).sum::<usize>(),
+            env.board().num_pellets_remaining()
+        );
+
+        for _ in 0..20 {
+            env.step(Action::Left);
+        }
+        assert_eq!(env.steps(), 20);
+        assert_eq!(env.reset(3), first);
+        assert_eq!(env.steps(), 0);
+    }
+
+    #[test]
+    fn steps_are_rewarded_for_their_events() {
+        let mut env = Env::new(EnvConfig {
+            rewards: Rewards {
+                tick: -1.0,
+                ..Rewards::default()
+            },
+            max_steps: Some(10),
+            ..EnvConfig::default()
+        });
+        env.reset(3);
+        let mut pellets = 0;
+        for step in 1..=10 {
+            let score = env.game().players.current().score;
+            let (obs, reward, done) = env.step(Action::Left);
+            // heading left from the start only passes plain pellets, 10 points
+            // each, and every step is 4 ticks
+            let eaten = (obs.score - score) / 10;
+            assert_eq!(reward, eaten as f32 - 4.0, "step {}", step);
+            assert_eq!(done, step == 10);
+            pellets += eaten;
+        }
+        assert!(pellets > 0);
+    }
+}
@@ -0,0 +1,122 @@
+// Run the game core with no window, audio or clock.
+//
+// Ticks run back to back as fast as the CPU allows, with input coming from
+// whatever `InputSource` the caller hands in. Nothing here needs SDL, so this
+// works on CI machines and in batch jobs.
+use crate::config::Config;
+use crate::game::{Game, GhostKind};
+use crate::ghost_ai::GhostAi;
+use crate::input::GameInput;
+
+// Where the joystick comes from when there's no keyboard
+pub trait InputSource {
+    fn next_input(&mut self, game: &Game) -> GameInput;
+}
+
+// any closure can drive the game
+impl<F: FnMut(&Game) -> GameInput> InputSource for F {
+    fn next_input(&mut self, game: &Game) -> GameInput {
+        self(game)
+    }
+}
+
+// A fixed list of inputs, one per tick. Nil once it runs out.
+pub struct ScriptedInput {
+    pub inputs: Vec<GameInput>,
+    pub pos: usize,
+}
+
+impl ScriptedInput {
+    pub fn new(inputs: Vec<GameInput>) -> ScriptedInput {
+        ScriptedInput { inputs, pos: 0 }
+    }
+
+    // (input, ticks held) pairs, the way a player would describe it
+    pub fn from_held(held: &[(GameInput, u32)]) -> ScriptedInput {
+        let inputs = held
+            .iter()
+            .flat_map(|&(input, ticks)| std::iter::repeat_n(input, ticks as usize))
+            .collect();
+        ScriptedInput::new(inputs)
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.pos >= self.inputs.len()
+    }
+}
+
+impl InputSource for ScriptedInput {
+    fn next_input(&mut self, _game: &Game) -> GameInput {
+        let input = self.inputs.get(self.pos).cloned().unwrap_or(GameInput::Nil);
+        self.pos += 1;
+        input
+    }
+}
+
+// What a run ended with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RunSummary {
+    pub ticks: u64,
+    pub score: u32,
+    pub level: u32,
+    pub lives: u32,
+    pub game_over: bool,
+}
+
+pub struct HeadlessRunner {
+    pub game: Game,
+}
+
+impl HeadlessRunner {
+    pub fn new(config: Config, num_players: usize, seed: u64) -> HeadlessRunner {
+        HeadlessRunner {
+            game: Game::new(config, num_players, seed),
+        }
+    }
+
+    pub fn from_game(game: Game) -> HeadlessRunner {
+        HeadlessRunner { game }
+    }
+
+    // Swap in an experimental AI, e.g. to run the same seeds and inputs with
+    // and without it and compare the summaries
+    pub fn with_ghost_ai(mut self, kind: GhostKind, ai: Box<dyn GhostAi>) -> HeadlessRunner {
+        self.game.set_ghost_ai(kind, ai);
+        self
+    }
+
+    // Run up to `ticks` ticks, stopping early if the game ends
+    pub fn run(&mut self, ticks: u64, input: &mut dyn InputSource) -> RunSummary {
+        self.run_until(ticks, input, |_| false)
+    }
+
+    // Like `run`, also stopping as soon as `stop` returns true after a tick
+    pub fn run_until(
+        &mut self,
+        max_ticks: u64,
+        input: &mut dyn InputSource,
+        mut stop: impl FnMut(&Game) -> bool,
+    ) -> RunSummary {
+        let mut ticks = 0;
+        while ticks < max_ticks && !self.game.is_game_over() {
+            let next = input.next_input(&self.game);
+            self.game.tick(next);
+            ticks += 1;
+            if stop(&self.game) {
+                break;
+            }
+        }
+        self.summary(ticks)
+    }
+
+    fn summary(&self, ticks: u64) -> RunSummary {
+        let player = self.game.players.current();
+        RunSummary {
+            ticks,
+            score: player.score,
+            level: player.level,
+            lives: player.lives,
+            game_over: self.game.is_game_over(),
+        }
+    }
+}
@@ -1,6 +1,7 @@
-use piston_window::*;
+#[cfg(feature = "sdl")]
+use sdl2::keyboard::{KeyboardState, Keycode, Scancode};
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum GameInput {
     Down,
     Up,
@@ -10,38 +11,67 @@ pub enum GameInput {
     Nil,
 }
 
-impl From<&keyboard::Key> for GameInput {
-    fn from(key: &keyboard::Key) -> GameInput {
+impl GameInput {
+    // one byte per input in replays
+    pub fn to_u8(self) -> u8 {
+        match self {
+            GameInput::Down => 0,
+            GameInput::Up => 1,
+            GameInput::Left => 2,
+            GameInput::Right => 3,
+            GameInput::Step => 4,
+            GameInput::Nil => 5,
+        }
+    }
+
+    pub fn from_u8(v: u8) -> Option<GameInput> {
+        match v {
+            0 => Some(GameInput::Down),
+            1 => Some(GameInput::Up),
+            2 => Some(GameInput::Left),
+            3 => Some(GameInput::Right),
+            4 => Some(GameInput::Step),
+            5 => Some(GameInput::Nil),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "sdl")]
+impl From<&Keycode> for GameInput {
+    fn from(key: &Keycode) -> GameInput {
         GameInput::from(*key)
     }
 }
-impl From<keyboard::Key> for GameInput {
-    fn from(key: keyboard::Key) -> GameInput {
-        return match key {
-            keyboard::Key::Up => GameInput::Up,
-            keyboard::Key::Left => GameInput::Left,
-            keyboard::Key::Down => GameInput::Down,
-            keyboard::Key::Right => GameInput::Right,
-            keyboard::Key::Space => GameInput::Step,
+#[cfg(feature = "sdl")]
+impl From<Keycode> for GameInput {
+    fn from(key: Keycode) -> GameInput {
+        match key {
+            Keycode::Up => GameInput::Up,
+            Keycode::Left => GameInput::Left,
+            Keycode::Down => GameInput::Down,
+            Keycode::Right => GameInput::Right,
+            Keycode::Space => GameInput::Step,
             _ => GameInput::Nil,
         }
     }
 }
 
-// There has to be an easier or nicer way to go
-// Keyboard -> Input, Gamepad -> Input, w/ out having to deal with these different types
-impl From<piston_window::Input> for GameInput {
-    fn from(piston_input: piston_window::Input) -> GameInput {
-        // if the input event was a button event
-        if let piston_window::Input::Button(button_args) = piston_input {
-            // if there was a button press
-            if button_args.state == ButtonState::Press {
-                // if is a keyboard button
-                if let Button::Keyboard(keyboard_button) = button_args.button {
-                    return GameInput::from(keyboard_button);
-                }
-            }
+#[cfg(feature = "sdl")]
+impl GameInput {
+    // The joystick is held, not pressed, so the simulation wants whichever
+    // direction is down this tick rather than KeyDown events.
+    pub fn from_keyboard_state(keyboard: &KeyboardState) -> GameInput {
+        if keyboard.is_scancode_pressed(Scancode::Up) {
+            GameInput::Up
+        } else if keyboard.is_scancode_pressed(Scancode::Left) {
+            GameInput::Left
+        } else if keyboard.is_scancode_pressed(Scancode::Down) {
+            GameInput::Down
+        } else if keyboard.is_scancode_pressed(Scancode::Right) {
+            GameInput::Right
+        } else {
+            GameInput::Nil
         }
-        GameInput::Nil
     }
 }
@@ -0,0 +1,98 @@
+use crate::config::Difficulty;
+
+// Per-level tables from "The Pac-Man Dossier", Table A.1
+// Speeds are percentages of Pac-Man's max speed, as in the Dossier.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LevelSpec {
+    pub bonus_fruit: Fruit,
+    pub pacman_speed: u32,
+    pub pacman_fright_speed: u32,
+    pub ghost_speed: u32,
+    pub ghost_tunnel_speed: u32,
+    pub ghost_fright_speed: u32,
+    pub fright_secs: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fruit {
+    Cherries,
+    Strawberry,
+    Peach,
+    Apple,
+    Grapes,
+    Galaxian,
+    Bell,
+    Key,
+}
+
+impl Fruit {
+    pub fn points(self) -> u32 {
+        match self {
+            Fruit::Cherries => 100,
+            Fruit::Strawberry => 300,
+            Fruit::Peach => 500,
+            Fruit::Apple => 700,
+            Fruit::Grapes => 1000,
+            Fruit::Galaxian => 2000,
+            Fruit::Bell => 3000,
+            Fruit::Key => 5000,
+        }
+    }
+}
+
+// The arcade's level counter is a byte, level 256 is the split screen and
+// there's nothing after it
+pub const LAST_LEVEL: u32 = 256;
+
+// The hard table runs four levels ahead of the normal one, so a hard game starts
+// out at level 5 speeds and loses frightened time sooner.
+const HARD_LEVEL_OFFSET: u32 = 4;
+
+pub fn level_spec(level: u32, difficulty: Difficulty) -> LevelSpec {
+    assert!(level >= 1);
+    let speed_level = match difficulty {
+        Difficulty::Normal => level,
+        Difficulty::Hard => level.saturating_add(HARD_LEVEL_OFFSET),
+    };
+
+    // (pacman, pacman fright, ghost, ghost tunnel, ghost fright)
+    let (pacman_speed, pacman_fright_speed, ghost_speed, ghost_tunnel_speed, ghost_fright_speed) =
+        match speed_level {
+            1 => (80, 90, 75, 40, 50),
+            2..=4 => (90, 95, 85, 45, 55),
+            5..=20 => (100, 100, 95, 50, 60),
+            _ => (90, 90, 95, 50, 95),
+        };
+
+    let fright_secs = match speed_level {
+        1 => 6,
+        2 | 6 | 10 => 5,
+        3 => 4,
+        4 | 14 => 3,
+        5 | 7 | 8 | 11 => 2,
+        9 | 12 | 13 | 15 | 16 | 18 => 1,
+        _ => 0,
+    };
+
+    // the fruit is tied to the level, not the difficulty
+    let bonus_fruit = match level {
+        1 => Fruit::Cherries,
+        2 => Fruit::Strawberry,
+        3..=4 => Fruit::Peach,
+        5..=6 => Fruit::Apple,
+        7..=8 => Fruit::Grapes,
+        9..=10 => Fruit::Galaxian,
+        11..=12 => Fruit::Bell,
+        _ => Fruit::Key,
+    };
+
+    LevelSpec {
+        bonus_fruit,
+        pacman_speed,
+        pacman_fright_speed,
+        ghost_speed,
+        ghost_tunnel_speed,
+        ghost_fright_speed,
+        fright_secs,
+    }
+}
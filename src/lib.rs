@@ -0,0 +1,35 @@
+// The game core, shared by the `s` and `gl` binaries and anything else that
+// wants to drive or inspect a game.
+pub mod attract;
+pub mod batch;
+pub mod board;
+pub mod bot;
+pub mod cli;
+pub mod codec;
+pub mod config;
+pub mod cutscene;
+pub mod debugger;
+pub mod desync;
+pub mod event;
+pub mod font;
+pub mod game;
+pub mod ghost_ai;
+pub mod gym;
+pub mod headless;
+pub mod input;
+pub mod level;
+pub mod player;
+pub mod protocol;
+pub mod replay;
+pub mod rewind;
+pub mod rng;
+pub mod savestate;
+pub mod scenario;
+#[cfg(feature = "sdl")]
+pub mod sound;
+pub mod spectator;
+pub mod stats;
+pub mod tile_trace;
+pub mod timestep;
+pub mod trace;
+pub mod vec2;
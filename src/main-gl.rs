@@ -7,9 +7,51 @@ pub mod gl_render;
 use std::result::Result;
 use std::string::String;
 
+use pacman::board::Board;
+use pacman::bot::Autopilot;
+use pacman::cli::{Cli, Frontend};
+use pacman::cutscene::{self, Sprite};
+use pacman::debugger::Debugger;
+use pacman::font;
+use pacman::game::{self, Game, GhostKind, GhostState, Phase};
+use pacman::input::GameInput;
+use pacman::protocol;
+use pacman::replay::Replay;
+use pacman::spectator::SpectatorServer;
+use pacman::tile_trace::TileTraceWriter;
+use pacman::timestep::{self, FixedTimestep};
+use pacman::trace::TraceWriter;
+
 fn main() -> Result<(), String> {
-    const SCREEN_WIDTH: u32 = 1280;
-    const SCREEN_HEIGHT: u32 = 720;
+    let cli = Cli::from_env_or_exit(Frontend::Gl);
+    if cli.headless {
+        let summary = cli.run_headless()?;
+        println!("{:?}", summary);
+        return Ok(());
+    }
+    if let Some(games) = cli.batch {
+        let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+        print!("{}", cli.run_batch(games, cpus)?);
+        return Ok(());
+    }
+    if cli.verify {
+        if !cli.verify_replay()? {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if cli.compare.is_some() {
+        if !cli.compare_trace()? {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if cli.stdio {
+        return protocol::run_stdio(|seed| cli.new_game(1, seed), cli.seed());
+    }
+
+    let screen_width = 640 * cli.scale;
+    let screen_height = 360 * cli.scale;
 
     let sdl_context = sdl2::init()?;
 
@@ -19,82 +61,65 @@ fn main() -> Result<(), String> {
     gl_attr.set_context_profile(sdl2::video::GLProfile::Core);
     gl_attr.set_context_version(4, 5);
 
-    let sdl_window = sdl_video
-        .window("gl", SCREEN_WIDTH, SCREEN_HEIGHT)
-        .position_centered()
-        .opengl()
-        .build()
-        .map_err(|e| e.to_string())?;
+    let mut sdl_window = sdl_video.window("gl", screen_width, screen_height);
+    sdl_window.position_centered().opengl();
+    if cli.fullscreen {
+        sdl_window.fullscreen_desktop();
+    }
+    let sdl_window = sdl_window.build().map_err(|e| e.to_string())?;
 
     let _gl_context = sdl_window.gl_create_context().unwrap();
-    let _gl = gl::load_with(|s| sdl_video.gl_get_proc_address(s) as *const std::os::raw::c_void);
+    gl::load_with(|s| sdl_video.gl_get_proc_address(s) as *const std::os::raw::c_void);
 
     let mut events = sdl_context.event_pump()?;
 
     let target_fps = 30.0;
     let target_frame_duration = std::time::Duration::from_secs_f64(1.0 / target_fps);
 
-    let colors = [
-        sdl2::pixels::Color::RGB(255, 0, 0),
-        sdl2::pixels::Color::RGB(255, 255, 0),
-        sdl2::pixels::Color::RGB(0, 255, 0),
-        sdl2::pixels::Color::RGB(0, 255, 255),
-        sdl2::pixels::Color::RGB(0, 0, 255),
-        sdl2::pixels::Color::RGB(255, 0, 255),
-    ];
-    let mut color_cycler = colors.iter().cycle();
-
     //////////////////////////////////////////
     // Compile Shaders & Link programs
     // TODO: have a step for reading the files instead of just using `include_str!
-    let vert_source = include_str!("shaders/triangle.vert");
+    let vert_source = include_str!("shaders/game.vert");
     let vert_shader = gl_render::GlShader::from_source(vert_source, gl::VERTEX_SHADER)?;
 
-    let frag_source = include_str!("shaders/triangle.frag");
+    let frag_source = include_str!("shaders/game.frag");
     let frag_shader = gl_render::GlShader::from_source(frag_source, gl::FRAGMENT_SHADER)?;
 
     let shader_program = gl_render::GlProgram::from_shaders(&[vert_shader, frag_shader])?;
     gl_render::use_program(&shader_program);
 
-    /////////////////////////////////////////
-    // Rendering Data for VBO etc
-    let vertices: Vec<f32> = vec![-0.5, -0.5, 0.0, 0.5, -0.5, 0.0, 0.0, 0.5, 0.0];
-    // create and setup the vertex buffer object
-    let vbo_handle: gl::types::GLuint = unsafe {
-        let mut vbo_handle: gl::types::GLuint = 0;
-        gl::GenBuffers(1, &mut vbo_handle);
-        gl::BindBuffer(gl::ARRAY_BUFFER, vbo_handle);
-        gl::BufferData(
-            gl::ARRAY_BUFFER,                                                       //target
-            (vertices.len() * std::mem::size_of::<f32>()) as gl::types::GLsizeiptr, //size
-            vertices.as_ptr() as *const gl::types::GLvoid,                          //data
-            gl::STATIC_DRAW,                                                        //usage
-        );
+    let mut rects = gl_render::RectBatch::new(SCREEN_WIDTH, SCREEN_HEIGHT);
 
-        // unbind the buffer
-        gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-        vbo_handle
+    // the game runs at the arcade's rate no matter the frame rate
+    let (mut game, mut playback, seed) = match &cli.replay {
+        Some(path) => {
+            let replay = Replay::load(path)?;
+            (replay.new_game(), Some(replay.playback()), replay.seed)
+        }
+        None => {
+            let seed = cli.seed();
+            (cli.new_game(1, seed), None, seed)
+        }
     };
-
-    // create and setup a vertex arary object aka DataLayout
-    let vao_handle: gl::types::GLuint = unsafe {
-        let mut vao_handle: gl::types::GLuint = 0;
-        gl::GenVertexArrays(1, &mut vao_handle);
-        gl::BindVertexArray(vao_handle);
-        gl::BindBuffer(gl::ARRAY_BUFFER, vbo_handle);
-        gl::EnableVertexAttribArray(0); // this is `layout (location = 0)` in vertex.shader
-        gl::VertexAttribPointer(
-            0,         // index of the vertex attribute  `layout (location = 0)`
-            3,         // number of components per attribute
-            gl::FLOAT, // data type
-            gl::FALSE, // normalized
-            (3 * std::mem::size_of::<f32>()) as gl::types::GLint, // stride
-            std::ptr::null(), // initial offest
-        );
-        gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-        gl::BindVertexArray(0);
-        vao_handle
+    let mut recording = cli
+        .record
+        .as_ref()
+        .map(|_| Replay::new(game.config, game.players.num_players(), seed));
+    let mut timestep = FixedTimestep::arcade();
+    // same keys as the sdl binary, see `Debugger::handle_key`
+    let mut debugger = Debugger::new();
+    debugger.paused = cli.debug;
+    let autopilot = Autopilot::new();
+    let mut spectator = match cli.listen {
+        Some(port) => Some(SpectatorServer::bind(port)?),
+        None => None,
     };
+    let mut trace = cli.trace.as_deref().map(TraceWriter::create).transpose()?;
+    let mut tile_trace = cli
+        .tile_trace
+        .as_deref()
+        .map(TileTraceWriter::create)
+        .transpose()?;
 
     'main: loop {
         let frame_start_time = std::time::Instant::now();
@@ -108,33 +133,87 @@ fn main() -> Result<(), String> {
                     keycode: Some(sdl2::keyboard::Keycode::Escape),
                     ..
                 } => break 'main,
+                // any key the debugger doesn't use skips a cutscene
+                sdl2::event::Event::KeyDown {
+                    keycode: Some(keycode),
+                    ..
+                } if !debugger.handle_key(keycode) => game.skip_intermission(),
                 _ => {}
             }
         }
         //////////////////////////////////////////
         // UPDATE
+        let report = timestep.advance(std::time::Instant::now());
+        if report.is_overrun() {
+            println!("WARNING: FELL BEHIND, DROPPED {} TICKS", report.dropped);
+        }
+        let keyboard_input = GameInput::from_keyboard_state(&events.keyboard_state());
+        if let Some(server) = &mut spectator {
+            if server.poll(Some(&mut game), &mut debugger) {
+                // recordings can't follow an edited game
+                playback = None;
+                if recording.take().is_some() {
+                    println!("stopped recording, the game was edited");
+                }
+            }
+        }
+        for _ in 0..report.ticks {
+            if !debugger.should_tick() {
+                continue;
+            }
+            let input = match &mut playback {
+                Some(p) => p.take_input(),
+                None if cli.bot => autopilot.choose_input(&game),
+                None => keyboard_input,
+            };
+            // a spectator loading a state replaces the game
+            game.trace_decisions = trace.is_some();
+            game.tick(input);
+            if let Some(t) = &mut trace {
+                t.write_tick(&game)?;
+            }
+            if let Some(t) = &mut tile_trace {
+                t.write_tick(&game)?;
+            }
+            if let Some(r) = &mut recording {
+                r.record(input, &game);
+            }
+            if let Some(tick) = playback.as_mut().and_then(|p| p.check(&game)) {
+                println!("replay desynced at tick {}, --verify shows how", tick);
+            }
+            debugger.after_tick(&game);
+            if let Some(server) = &mut spectator {
+                server.send_tick(&game);
+            }
+        }
+        if let Some(server) = &mut spectator {
+            for notice in server.take_notices() {
+                println!("{}", notice);
+            }
+        }
         //////////////////////////////////////////
         // RENDER
-        // clear background
         unsafe {
-            let color = color_cycler.next().unwrap();
-            let r = color.r as f32 / 255.0;
-            let g = color.g as f32 / 255.0;
-            let b = color.b as f32 / 255.0;
-            gl::Viewport(0, 0, SCREEN_WIDTH as i32, SCREEN_HEIGHT as i32);
-            gl::ClearColor(r, g, b, 1.0);
+            // letterboxed to keep the pixels square
+            let (width, height) = sdl_window.drawable_size();
+            let scale =
+                (width as f32 / SCREEN_WIDTH as f32).min(height as f32 / SCREEN_HEIGHT as f32);
+            let (view_width, view_height) = (
+                (SCREEN_WIDTH as f32 * scale) as i32,
+                (SCREEN_HEIGHT as f32 * scale) as i32,
+            );
+            gl::Viewport(
+                (width as i32 - view_width) / 2,
+                (height as i32 - view_height) / 2,
+                view_width,
+                view_height,
+            );
+            gl::ClearColor(0.0, 0.0, 0.0, 1.0);
             gl::Clear(gl::COLOR_BUFFER_BIT);
         }
-        // draw triangle
+        render_game(&mut rects, &game);
         gl_render::use_program(&shader_program);
-        unsafe {
-            gl::BindVertexArray(vao_handle);
-            gl::DrawArrays(
-                gl::TRIANGLES, // mode
-                0,             // starting index
-                3,             // number of elements to render
-            );
-        }
+        rects.draw();
         sdl_window.gl_swap_window();
 
         //////////////////////////////////////////
@@ -147,11 +226,172 @@ fn main() -> Result<(), String> {
                 (elapsed_time / target_time) - 1.0
             );
         } else {
-            // waste time: `while` loop more accurate than `std::thread::sleep`
-            while frame_start_time.elapsed() < target_frame_duration {}
+            timestep::sleep_until(frame_start_time + target_frame_duration);
         }
         // TODO: render FPS to screen
     }
 
+    if let (Some(recording), Some(path)) = (recording, &cli.record) {
+        recording.save(path)?;
+    }
+    if let Some(trace) = trace {
+        trace.finish()?;
+    }
+    if let Some(tile_trace) = tile_trace {
+        tile_trace.finish()?;
+    }
+
     Ok(())
 }
+
+// The same layout and colors as the sdl binary's game screen
+const SCREEN_WIDTH: u32 = 320;
+const SCREEN_HEIGHT: u32 = 256;
+const HUD_X: i32 = 232;
+
+type Rgb = (u8, u8, u8);
+
+fn ghost_color(kind: GhostKind) -> Rgb {
+    match kind {
+        GhostKind::Blinky => (255, 0, 0),
+        GhostKind::Pinky => (255, 184, 255),
+        GhostKind::Inky => (0, 255, 255),
+        GhostKind::Clyde => (255, 184, 82),
+    }
+}
+
+fn draw_text(rects: &mut gl_render::RectBatch, text: &str, x: i32, y: i32, color: Rgb) {
+    for (i, c) in text.chars().enumerate() {
+        let glyph_x = x + i as i32 * (font::GLYPH_WIDTH as i32 + 1);
+        for (row, bits) in font::glyph(c).iter().enumerate() {
+            for col in 0..font::GLYPH_WIDTH {
+                if bits & (1 << (font::GLYPH_WIDTH - 1 - col)) != 0 {
+                    rects.push(glyph_x + col as i32, y + row as i32, 1, 1, color);
+                }
+            }
+        }
+    }
+}
+
+fn render_cutscene(rects: &mut gl_render::RectBatch, cutscene: &cutscene::Cutscene) {
+    for (sprite, pos) in cutscene.actors() {
+        let x = pos.x.floor_pixels();
+        let y = pos.y.floor_pixels();
+        match sprite {
+            Sprite::Pacman => rects.push(x, y, 8, 8, (255, 255, 0)),
+            Sprite::GiantPacman => rects.push(x, y - 24, 32, 32, (255, 255, 0)),
+            Sprite::Blinky | Sprite::BlinkyTornCloak | Sprite::BlinkyPatchedCloak => {
+                rects.push(x, y, 8, 8, (255, 0, 0))
+            }
+            Sprite::FrightenedBlinky => rects.push(x, y, 8, 8, (33, 33, 255)),
+            Sprite::BlinkyNaked => rects.push(x + 2, y + 2, 4, 6, (255, 184, 151)),
+            Sprite::Nail => rects.push(x, y + 5, 2, 3, (222, 222, 222)),
+        }
+
+        // the cloak details
+        match sprite {
+            Sprite::BlinkyTornCloak => rects.push(x, y + 6, 3, 2, (0, 0, 0)),
+            Sprite::BlinkyPatchedCloak => rects.push(x + 1, y + 5, 3, 2, (255, 184, 151)),
+            _ => {}
+        }
+    }
+}
+
+fn render_game(rects: &mut gl_render::RectBatch, game: &Game) {
+    if let Phase::Intermission(cutscene) = &game.phase {
+        render_cutscene(rects, cutscene);
+        return;
+    }
+
+    let board = game.board();
+    render_board(rects, board);
+
+    // ghost house door
+    for h in 0..board.num_tiles {
+        if board.tile_is_ghost_door(h) {
+            let pos = board.get_local_pos_of_tile(h);
+            rects.push(
+                pos.x.floor_pixels(),
+                pos.y.floor_pixels() + 3,
+                8,
+                2,
+                (255, 184, 255),
+            );
+        }
+    }
+
+    if let Some(_fruit) = game.bonus_fruit {
+        let pos = game::tile_pos(game::FRUIT_POS);
+        rects.push(
+            pos.x.floor_pixels() + 1,
+            pos.y.floor_pixels() + 1,
+            6,
+            6,
+            (255, 0, 0),
+        );
+    }
+
+    rects.push(
+        game.pacman.pos.x.floor_pixels(),
+        game.pacman.pos.y.floor_pixels(),
+        8,
+        8,
+        (255, 255, 0),
+    );
+
+    for ghost in game.ghosts.iter() {
+        let x = ghost.pos.x.floor_pixels();
+        let y = ghost.pos.y.floor_pixels();
+        let eyes_only =
+            ghost.state == GhostState::Eaten || ghost.state == GhostState::EnteringHouse;
+        if !eyes_only {
+            let color = if ghost.frightened {
+                // flash white as the power pellet wears off
+                if game.fright_ticks < 2 * game::TICKS_PER_SECOND
+                    && (game.fright_ticks / 15).is_multiple_of(2)
+                {
+                    (255, 255, 255)
+                } else {
+                    (33, 33, 255)
+                }
+            } else {
+                ghost_color(ghost.kind)
+            };
+            rects.push(x, y, 8, 8, color);
+        }
+        rects.push(x + 1, y + 2, 2, 2, (255, 255, 255));
+        rects.push(x + 5, y + 2, 2, 2, (255, 255, 255));
+    }
+
+    // HUD
+    let white = (255, 255, 255);
+    for i in 0..game.players.num_players() {
+        let player = game.players.get(i);
+        let y = 8 + i as i32 * 24;
+        draw_text(rects, &format!("{}UP", i + 1), HUD_X, y, white);
+        draw_text(rects, &player.score.to_string(), HUD_X, y + 8, white);
+    }
+    let player = game.players.current();
+    draw_text(rects, &format!("LIVES {}", player.lives), HUD_X, 64, white);
+    draw_text(rects, &format!("LEVEL {}", player.level), HUD_X, 72, white);
+
+    match game.phase {
+        Phase::Ready(_) => draw_text(rects, "READY!", 100, 138, (255, 255, 0)),
+        Phase::GameOver => draw_text(rects, "GAME  OVER", 92, 138, (255, 0, 0)),
+        _ => {}
+    }
+}
+
+fn render_board(rects: &mut gl_render::RectBatch, board: &Board) {
+    for h in 0..board.num_tiles {
+        let pos = board.get_local_pos_of_tile(h);
+        let (x, y) = (pos.x.floor_pixels(), pos.y.floor_pixels());
+        if !board.tile_is_traversable(h) {
+            rects.push(x, y, 8, 8, (0, 0, 150));
+        } else if board.tile_has_pellet(h) || board.tile_has_power_pellet(h) {
+            let size = if board.tile_has_power_pellet(h) { 6 } else { 2 };
+            let offset = (8 - size) as i32 / 2;
+            rects.push(x + offset, y + offset, size, size, (255, 184, 151));
+        }
+    }
+}
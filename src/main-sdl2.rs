@@ -1,10 +1,24 @@
-use sdl2::image::LoadTexture;
-
-mod board;
-use crate::board::*;
-
-mod vec2;
-type Vec2 = cgmath::Vector2<f32>;
+use pacman::attract::{Attract, AttractStage, IntroReveal};
+use pacman::board::*;
+use pacman::bot::Autopilot;
+use pacman::cli::{Cli, Frontend};
+use pacman::config::Config;
+use pacman::cutscene::{self, Sprite};
+use pacman::debugger::Debugger;
+use pacman::event::{self, EventListener, GameEvent};
+use pacman::font;
+use pacman::game::{self, Game, GhostKind, GhostState, Phase};
+use pacman::input::GameInput;
+use pacman::protocol;
+use pacman::replay::{Replay, ReplayPlayback};
+use pacman::rewind::{RewindBuffer, RewindConfig};
+use pacman::savestate;
+use pacman::sound::SoundPlayer;
+use pacman::spectator::SpectatorServer;
+use pacman::tile_trace::TileTraceWriter;
+use pacman::timestep::{self, FixedTimestep};
+use pacman::trace::TraceWriter;
+use pacman::vec2::Vec2;
 
 fn main() -> std::result::Result<(), std::string::String> {
     let cache_line_size = sdl2::cpuinfo::cpu_cache_line_size();
@@ -12,16 +26,81 @@ fn main() -> std::result::Result<(), std::string::String> {
     dbg!(cache_line_size);
     dbg!(num_cpus);
 
+    let cli = Cli::from_env_or_exit(Frontend::Sdl);
+    let config = cli.config;
+
+    if cli.headless {
+        let summary = cli.run_headless()?;
+        println!("{:?}", summary);
+        return Ok(());
+    }
+    // --batch runs one game per CPU at a time
+    if let Some(games) = cli.batch {
+        print!("{}", cli.run_batch(games, num_cpus as usize)?);
+        return Ok(());
+    }
+    if cli.verify {
+        if !cli.verify_replay()? {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if cli.compare.is_some() {
+        if !cli.compare_trace()? {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if cli.stdio {
+        return protocol::run_stdio(|seed| cli.new_game(1, seed), cli.seed());
+    }
+
+    // --record <path>: save each game's inputs, --replay <path>: play one back
+    let record_path = cli.record.clone();
+    // F5 saves the game here, F9 loads it
+    let state_path = cli.state.clone();
+    // hold backspace to rewind, --rewind-mb sets how much history is kept
+    let mut rewind = RewindBuffer::new(RewindConfig {
+        memory_budget_bytes: cli
+            .rewind_mb
+            .checked_mul(1024 * 1024)
+            .ok_or("--rewind-mb is too big")?,
+        ..RewindConfig::default()
+    });
+    // P pauses, Space steps, see `Debugger::handle_key`. --debug starts games paused
+    let mut debugger = Debugger::new();
+    // --bot plays instead of the keyboard
+    let autopilot = Autopilot::new();
+    // --listen <port>: inspector tools can watch and debug over TCP
+    let mut spectator = match cli.listen {
+        Some(port) => Some(SpectatorServer::bind(port)?),
+        None => None,
+    };
+    // --trace <path>: every ghost decision and Pac-Man turn, for every game played
+    let mut trace = cli.trace.as_deref().map(TraceWriter::create).transpose()?;
+    // --tile-trace <path>: where everyone is after each tick, see tile_trace.rs
+    let mut tile_trace = cli
+        .tile_trace
+        .as_deref()
+        .map(TileTraceWriter::create)
+        .transpose()?;
+
     let sdl_context = sdl2::init()?;
 
     let mut event_pump = sdl_context.event_pump()?;
+    let mut sound = SoundPlayer::new(sdl_context.audio()?, std::path::Path::new("assets/sounds"));
 
     let video_subsystem = sdl_context.video()?;
-    let window = video_subsystem
-        .window("fun times", 1280, 720)
-        .position_centered()
-        .build()
-        .unwrap();
+    let mut window = video_subsystem.window(
+        "fun times",
+        SCREEN_WIDTH * cli.scale,
+        SCREEN_HEIGHT * cli.scale,
+    );
+    window.position_centered();
+    if cli.fullscreen {
+        window.fullscreen_desktop();
+    }
+    let window = window.build().map_err(|e| e.to_string())?;
     let _image_context =
         sdl2::image::init(sdl2::image::InitFlag::PNG | sdl2::image::InitFlag::JPG)?;
     let mut canvas = window
@@ -29,12 +108,27 @@ fn main() -> std::result::Result<(), std::string::String> {
         .software()
         .build()
         .map_err(|e| e.to_string())?;
+    // the arcade screen is tiny, draw everything at --scale times the size
+    canvas.set_scale(cli.scale as f32, cli.scale as f32)?;
 
     let target_fps = 60.0;
     let target_frame_duration = std::time::Duration::from_secs_f64(1.0 / target_fps);
 
     // Section: GameSpecific
-    let board = Board::new();
+    let mut attract = Attract::new(config);
+    let mut game: Option<Game> = None;
+    // how long the GAME OVER screen has been up
+    let mut game_over_ticks = 0;
+    let mut popups = ScorePopups::default();
+    let mut timestep = FixedTimestep::arcade();
+
+    let mut recording: Option<Replay> = None;
+    let mut playback: Option<ReplayPlayback> = None;
+    if let Some(path) = &cli.replay {
+        let replay = Replay::load(path)?;
+        game = Some(replay.new_game());
+        playback = Some(replay.playback());
+    }
 
     'mainloop: loop {
         let frame_start_time = std::time::Instant::now();
@@ -44,6 +138,7 @@ fn main() -> std::result::Result<(), std::string::String> {
             match event {
                 sdl2::event::Event::Quit { .. } => {
                     println!("Event::Quit");
+                    save_recording(recording.take(), &record_path)?;
                     break 'mainloop;
                 }
                 sdl2::event::Event::AppTerminating { .. } => {
@@ -57,37 +152,494 @@ fn main() -> std::result::Result<(), std::string::String> {
                     ..
                 } => {
                     dbg!(keycode);
+                    let save_states_work = cli.maze.is_none();
+                    if !save_states_work
+                        && (keycode == sdl2::keyboard::Keycode::F5
+                            || keycode == sdl2::keyboard::Keycode::F9)
+                    {
+                        println!("save states only work with the arcade maze");
+                    } else if keycode == sdl2::keyboard::Keycode::F5 {
+                        if let Some(g) = &game {
+                            savestate::save(g, &state_path)?;
+                            println!("saved state to {}", state_path.display());
+                        }
+                    } else if keycode == sdl2::keyboard::Keycode::F9 {
+                        match savestate::load(&state_path) {
+                            Ok(loaded) => {
+                                game = Some(loaded);
+                                game_over_ticks = 0;
+                                rewind.clear();
+                                // the loaded game didn't start from this recording
+                                playback = None;
+                                if recording.take().is_some() {
+                                    println!("stopped recording, a save state was loaded");
+                                }
+                                println!("loaded state from {}", state_path.display());
+                            }
+                            Err(e) => println!("{}", e),
+                        }
+                    } else if game.is_some() && debugger.handle_key(keycode) {
+                        // handled by the debugger
+                    } else if let Some(g) = &mut game {
+                        g.skip_intermission();
+                    } else {
+                        let num_players = match keycode {
+                            sdl2::keyboard::Keycode::Num1 => Some(1),
+                            sdl2::keyboard::Keycode::Num2 => Some(2),
+                            _ => None,
+                        };
+                        match num_players {
+                            Some(n) => {
+                                let seed = cli.seed();
+                                game = Some(cli.new_game(n, seed));
+                                game_over_ticks = 0;
+                                rewind.clear();
+                                debugger = Debugger::new();
+                                debugger.paused = cli.debug;
+                                popups = ScorePopups::default();
+                                sound.play_begin();
+                                if record_path.is_some() {
+                                    recording = Some(Replay::new(config, n, seed));
+                                }
+                            }
+                            None => attract.on_key(),
+                        }
+                    }
                 }
                 _ => {}
             }
         }
 
-        // render board
-        render_board(&mut canvas, &board);
-        canvas.present();
+        // update, as many ticks as are due
+        let report = timestep.advance(std::time::Instant::now());
+        if report.is_overrun() {
+            println!("WARNING: FELL BEHIND, DROPPED {} TICKS", report.dropped);
+        }
+        let keyboard_input = GameInput::from_keyboard_state(&event_pump.keyboard_state());
+        let rewinding = event_pump
+            .keyboard_state()
+            .is_scancode_pressed(sdl2::keyboard::Scancode::Backspace);
+        if let Some(server) = &mut spectator {
+            if server.poll(game.as_mut(), &mut debugger) {
+                // recordings can't follow an edited game
+                playback = None;
+                if recording.take().is_some() {
+                    println!("stopped recording, the game was edited");
+                }
+            }
+        }
+        for _ in 0..report.ticks {
+            match &mut game {
+                Some(g) => {
+                    if rewinding {
+                        if let Some(previous) = rewind.rewind() {
+                            *g = previous;
+                            game_over_ticks = 0;
+                        }
+                        // recordings only go forwards
+                        playback = None;
+                        if recording.take().is_some() {
+                            println!("stopped recording, the game was rewound");
+                        }
+                        continue;
+                    }
+                    if !debugger.should_tick() {
+                        continue;
+                    }
 
-        // idle
-        if frame_start_time.elapsed() > target_frame_duration {
-            let elapsed_time = frame_start_time.elapsed().as_secs_f64();
-            let target_time = target_frame_duration.as_secs_f64();
-            //println!(
-            //    "WARNING: OVER TIME BUDGET BY {:.2}%)",
-            //    (elapsed_time / target_time) - 1.0
-            //);
-        } else {
-            // waste time
-            // reason added: this is much more accurate than a call to `std::thread::sleep`
-            // a goal of this engine is a high level of precision on per frame updates
-            while frame_start_time.elapsed() < target_frame_duration {}
+                    let input = match &mut playback {
+                        Some(p) => p.take_input(),
+                        None if cli.bot => autopilot.choose_input(g),
+                        None => keyboard_input,
+                    };
+                    rewind.record(g, input);
+                    // games also come from replays, save states and rewinds
+                    g.trace_decisions = trace.is_some();
+                    g.tick(input);
+                    if let Some(t) = &mut trace {
+                        t.write_tick(g)?;
+                    }
+                    if let Some(t) = &mut tile_trace {
+                        t.write_tick(g)?;
+                    }
+                    if let Some(r) = &mut recording {
+                        r.record(input, g);
+                    }
+                    if let Some(tick) = playback.as_mut().and_then(|p| p.check(g)) {
+                        println!("replay desynced at tick {}, --verify shows how", tick);
+                    }
+                    debugger.after_tick(g);
+                    popups.tick();
+                    event::dispatch(g, &mut [&mut sound, &mut popups]);
+                    if let Some(server) = &mut spectator {
+                        server.send_tick(g);
+                    }
+
+                    if g.is_game_over() {
+                        game_over_ticks += 1;
+                    }
+                    let playback_finished = playback.as_ref().is_some_and(|p| p.is_finished());
+                    if game_over_ticks > GAME_OVER_SCREEN_TICKS || playback_finished {
+                        game = None;
+                        playback = None;
+                        attract = Attract::new(config);
+                        save_recording(recording.take(), &record_path)?;
+                    }
+                }
+                None => attract.tick(),
+            }
+        }
+        if let Some(server) = &mut spectator {
+            for notice in server.take_notices() {
+                println!("{}", notice);
+            }
         }
 
+        // render
+        canvas.set_draw_color(sdl2::pixels::Color::RGB(0, 0, 0));
+        canvas.clear();
+        match &game {
+            Some(g) => {
+                render_game(&mut canvas, g);
+                popups.render(&mut canvas);
+                if debugger.paused {
+                    render_debugger(&mut canvas, &debugger);
+                }
+            }
+            None => render_attract(&mut canvas, &attract, &config),
+        }
+        canvas.present();
+
+        // idle until the next frame
+        // the simulation keeps its own time so it doesn't matter if this runs late
+        timestep::sleep_until(frame_start_time + target_frame_duration);
+
         //println!("FPS: {:.2}", 1.0 / frame_start_time.elapsed().as_secs_f64());
         //dbg!(frame_start_time.elapsed().as_micros());
     }
 
+    if let Some(trace) = trace {
+        trace.finish()?;
+    }
+    if let Some(tile_trace) = tile_trace {
+        tile_trace.finish()?;
+    }
+    Ok(())
+}
+
+const GAME_OVER_SCREEN_TICKS: u32 = 3 * game::TICKS_PER_SECOND;
+
+// the arcade screen, before --scale
+const SCREEN_WIDTH: u32 = 320;
+const SCREEN_HEIGHT: u32 = 256;
+
+// x position of the score etc, just right of the maze
+const HUD_X: i32 = 232;
+
+fn save_recording(
+    recording: Option<Replay>,
+    path: &Option<std::path::PathBuf>,
+) -> Result<(), String> {
+    if let (Some(recording), Some(path)) = (recording, path) {
+        recording.save(path)?;
+        println!("saved replay to {}", path.display());
+    }
     Ok(())
 }
 
+// Points shown where a ghost or fruit was eaten
+#[derive(Default)]
+struct ScorePopups {
+    popups: Vec<(u32, Vec2, u32)>,
+}
+
+impl ScorePopups {
+    fn tick(&mut self) {
+        for popup in self.popups.iter_mut() {
+            popup.2 -= 1;
+        }
+        self.popups.retain(|popup| popup.2 > 0);
+    }
+
+    fn render(&self, canvas: &mut sdl2::render::Canvas<sdl2::video::Window>) {
+        let cyan = sdl2::pixels::Color::RGB(0, 255, 255);
+        for (points, pos, _) in self.popups.iter() {
+            draw_text(
+                canvas,
+                &points.to_string(),
+                pos.x.floor_pixels() - 2,
+                pos.y.floor_pixels() + 1,
+                cyan,
+            );
+        }
+    }
+}
+
+impl EventListener for ScorePopups {
+    fn on_event(&mut self, game: &Game, event: &GameEvent) {
+        let points = match event {
+            GameEvent::GhostEaten { points, .. } | GameEvent::FruitEaten { points, .. } => *points,
+            _ => return,
+        };
+        self.popups
+            .push((points, game.pacman.pos, game::TICKS_PER_SECOND));
+    }
+}
+
+fn render_debugger(canvas: &mut sdl2::render::Canvas<sdl2::video::Window>, debugger: &Debugger) {
+    let white = sdl2::pixels::Color::RGB(255, 255, 255);
+    draw_text(canvas, "PAUSED", HUD_X, 200, white);
+    if let Some(condition) = debugger.running_until() {
+        draw_text(canvas, &format!("UNTIL {:?}", condition), HUD_X, 208, white);
+    }
+}
+
+fn ghost_color(kind: GhostKind) -> sdl2::pixels::Color {
+    match kind {
+        GhostKind::Blinky => sdl2::pixels::Color::RGB(255, 0, 0),
+        GhostKind::Pinky => sdl2::pixels::Color::RGB(255, 184, 255),
+        GhostKind::Inky => sdl2::pixels::Color::RGB(0, 255, 255),
+        GhostKind::Clyde => sdl2::pixels::Color::RGB(255, 184, 82),
+    }
+}
+
+fn draw_text(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    text: &str,
+    x: i32,
+    y: i32,
+    color: sdl2::pixels::Color,
+) {
+    canvas.set_draw_color(color);
+    for (i, c) in text.chars().enumerate() {
+        let glyph_x = x + i as i32 * (font::GLYPH_WIDTH as i32 + 1);
+        for (row, bits) in font::glyph(c).iter().enumerate() {
+            for col in 0..font::GLYPH_WIDTH {
+                if bits & (1 << (font::GLYPH_WIDTH - 1 - col)) != 0 {
+                    let rect = sdl2::rect::Rect::new(glyph_x + col as i32, y + row as i32, 1, 1);
+                    canvas.fill_rect(rect).unwrap();
+                }
+            }
+        }
+    }
+}
+
+fn render_attract(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    attract: &Attract,
+    config: &Config,
+) {
+    let white = sdl2::pixels::Color::RGB(255, 255, 255);
+    match attract.stage {
+        AttractStage::Title => {
+            draw_text(
+                canvas,
+                "PAC-MAN",
+                98,
+                100,
+                sdl2::pixels::Color::RGB(255, 255, 0),
+            );
+            draw_text(canvas, "PUSH 1 OR 2 TO START", 72, 130, white);
+        }
+        AttractStage::Intro => {
+            draw_text(canvas, "CHARACTER / NICKNAME", 64, 40, white);
+            let names = config.ghost_names();
+            for kind in GhostKind::ALL.iter().cloned() {
+                let i = kind.index();
+                let y = 64 + i as i32 * 24;
+                let reveal = attract.intro_reveal(i);
+                if reveal >= IntroReveal::Picture {
+                    canvas.set_draw_color(ghost_color(kind));
+                    canvas
+                        .fill_rect(sdl2::rect::Rect::new(40, y - 2, 8, 8))
+                        .unwrap();
+                }
+                if reveal >= IntroReveal::Character {
+                    let text = format!("-{}", names[i].0);
+                    draw_text(canvas, &text, 64, y, ghost_color(kind));
+                }
+                if reveal >= IntroReveal::Nickname {
+                    let text = format!("\"{}\"", names[i].1);
+                    draw_text(canvas, &text, 144, y, ghost_color(kind));
+                }
+            }
+        }
+        AttractStage::Demo => {
+            if let Some(demo) = &attract.demo {
+                render_game(canvas, demo);
+            }
+            draw_text(
+                canvas,
+                "GAME  OVER",
+                92,
+                138,
+                sdl2::pixels::Color::RGB(255, 0, 0),
+            );
+        }
+    }
+}
+
+fn render_cutscene(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    cutscene: &cutscene::Cutscene,
+) {
+    let red = sdl2::pixels::Color::RGB(255, 0, 0);
+    for (sprite, pos) in cutscene.actors() {
+        let x = pos.x.floor_pixels();
+        let y = pos.y.floor_pixels();
+        let (color, rect) = match sprite {
+            Sprite::Pacman => (
+                sdl2::pixels::Color::RGB(255, 255, 0),
+                sdl2::rect::Rect::new(x, y, 8, 8),
+            ),
+            Sprite::GiantPacman => (
+                sdl2::pixels::Color::RGB(255, 255, 0),
+                sdl2::rect::Rect::new(x, y - 24, 32, 32),
+            ),
+            Sprite::Blinky | Sprite::BlinkyTornCloak | Sprite::BlinkyPatchedCloak => {
+                (red, sdl2::rect::Rect::new(x, y, 8, 8))
+            }
+            Sprite::FrightenedBlinky => (
+                sdl2::pixels::Color::RGB(33, 33, 255),
+                sdl2::rect::Rect::new(x, y, 8, 8),
+            ),
+            Sprite::BlinkyNaked => (
+                sdl2::pixels::Color::RGB(255, 184, 151),
+                sdl2::rect::Rect::new(x + 2, y + 2, 4, 6),
+            ),
+            Sprite::Nail => (
+                sdl2::pixels::Color::RGB(222, 222, 222),
+                sdl2::rect::Rect::new(x, y + 5, 2, 3),
+            ),
+        };
+        canvas.set_draw_color(color);
+        canvas.fill_rect(rect).unwrap();
+
+        // the cloak details
+        match sprite {
+            Sprite::BlinkyTornCloak => {
+                canvas.set_draw_color(sdl2::pixels::Color::RGB(0, 0, 0));
+                canvas
+                    .fill_rect(sdl2::rect::Rect::new(x, y + 6, 3, 2))
+                    .unwrap();
+            }
+            Sprite::BlinkyPatchedCloak => {
+                canvas.set_draw_color(sdl2::pixels::Color::RGB(255, 184, 151));
+                canvas
+                    .fill_rect(sdl2::rect::Rect::new(x + 1, y + 5, 3, 2))
+                    .unwrap();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn render_game(canvas: &mut sdl2::render::Canvas<sdl2::video::Window>, game: &Game) {
+    if let Phase::Intermission(cutscene) = &game.phase {
+        render_cutscene(canvas, cutscene);
+        return;
+    }
+
+    let board = game.board();
+    render_board(canvas, board);
+
+    // ghost house door
+    canvas.set_draw_color(sdl2::pixels::Color::RGB(255, 184, 255));
+    for h in 0..board.num_tiles {
+        if board.tile_is_ghost_door(h) {
+            let pos = board.get_local_pos_of_tile(h);
+            let rect = sdl2::rect::Rect::new(pos.x.floor_pixels(), pos.y.floor_pixels() + 3, 8, 2);
+            canvas.fill_rect(rect).unwrap();
+        }
+    }
+
+    if let Some(_fruit) = game.bonus_fruit {
+        let pos = game::tile_pos(game::FRUIT_POS);
+        canvas.set_draw_color(sdl2::pixels::Color::RGB(255, 0, 0));
+        canvas
+            .fill_rect(sdl2::rect::Rect::new(
+                pos.x.floor_pixels() + 1,
+                pos.y.floor_pixels() + 1,
+                6,
+                6,
+            ))
+            .unwrap();
+    }
+
+    canvas.set_draw_color(sdl2::pixels::Color::RGB(255, 255, 0));
+    let pacman_rect = sdl2::rect::Rect::new(
+        game.pacman.pos.x.floor_pixels(),
+        game.pacman.pos.y.floor_pixels(),
+        8,
+        8,
+    );
+    canvas.fill_rect(pacman_rect).unwrap();
+
+    for ghost in game.ghosts.iter() {
+        let x = ghost.pos.x.floor_pixels();
+        let y = ghost.pos.y.floor_pixels();
+        let eyes_only =
+            ghost.state == GhostState::Eaten || ghost.state == GhostState::EnteringHouse;
+        if !eyes_only {
+            let color = if ghost.frightened {
+                // flash white as the power pellet wears off
+                if game.fright_ticks < 2 * game::TICKS_PER_SECOND
+                    && (game.fright_ticks / 15).is_multiple_of(2)
+                {
+                    sdl2::pixels::Color::RGB(255, 255, 255)
+                } else {
+                    sdl2::pixels::Color::RGB(33, 33, 255)
+                }
+            } else {
+                ghost_color(ghost.kind)
+            };
+            canvas.set_draw_color(color);
+            canvas.fill_rect(sdl2::rect::Rect::new(x, y, 8, 8)).unwrap();
+        }
+        canvas.set_draw_color(sdl2::pixels::Color::RGB(255, 255, 255));
+        canvas
+            .fill_rect(sdl2::rect::Rect::new(x + 1, y + 2, 2, 2))
+            .unwrap();
+        canvas
+            .fill_rect(sdl2::rect::Rect::new(x + 5, y + 2, 2, 2))
+            .unwrap();
+    }
+
+    // HUD
+    let white = sdl2::pixels::Color::RGB(255, 255, 255);
+    for i in 0..game.players.num_players() {
+        let player = game.players.get(i);
+        let y = 8 + i as i32 * 24;
+        draw_text(canvas, &format!("{}UP", i + 1), HUD_X, y, white);
+        draw_text(canvas, &player.score.to_string(), HUD_X, y + 8, white);
+    }
+    let player = game.players.current();
+    draw_text(canvas, &format!("LIVES {}", player.lives), HUD_X, 64, white);
+    draw_text(canvas, &format!("LEVEL {}", player.level), HUD_X, 72, white);
+
+    match game.phase {
+        Phase::Ready(_) => {
+            draw_text(
+                canvas,
+                "READY!",
+                100,
+                138,
+                sdl2::pixels::Color::RGB(255, 255, 0),
+            );
+        }
+        Phase::GameOver => {
+            draw_text(
+                canvas,
+                "GAME  OVER",
+                92,
+                138,
+                sdl2::pixels::Color::RGB(255, 0, 0),
+            );
+        }
+        _ => {}
+    }
+}
+
 fn render_board(canvas: &mut sdl2::render::Canvas<sdl2::video::Window>, board: &Board) {
     const PX_WIDE: u32 = 8;
     const PX_HIGH: u32 = 8;
@@ -95,7 +647,20 @@ fn render_board(canvas: &mut sdl2::render::Canvas<sdl2::video::Window>, board: &
         if !board.tile_is_traversable(h) {
             let pos = board.get_local_pos_of_tile(h);
             canvas.set_draw_color(sdl2::pixels::Color::RGB(0, 0, 150));
-            let rect = sdl2::rect::Rect::new(pos.x as i32, pos.y as i32, PX_WIDE, PX_HIGH);
+            let rect =
+                sdl2::rect::Rect::new(pos.x.floor_pixels(), pos.y.floor_pixels(), PX_WIDE, PX_HIGH);
+            canvas.fill_rect(rect).unwrap();
+        } else if board.tile_has_pellet(h) || board.tile_has_power_pellet(h) {
+            let pos = board.get_local_pos_of_tile(h);
+            let size = if board.tile_has_power_pellet(h) { 6 } else { 2 };
+            let offset = (PX_WIDE - size) as i32 / 2;
+            canvas.set_draw_color(sdl2::pixels::Color::RGB(255, 184, 151));
+            let rect = sdl2::rect::Rect::new(
+                pos.x.floor_pixels() + offset,
+                pos.y.floor_pixels() + offset,
+                size,
+                size,
+            );
             canvas.fill_rect(rect).unwrap();
         }
     }
@@ -0,0 +1,112 @@
+use crate::board::Board;
+
+// Everything the arcade keeps per player in an alternating 2P game.
+// The maze is part of this: each player clears their own pellets.
+#[derive(Clone)]
+pub struct Player {
+    pub board: Board,
+    pub level: u32,
+    pub score: u32,
+    pub lives: u32,
+    pub bonus_life_awarded: bool,
+}
+
+impl Player {
+    pub fn new(lives: u32) -> Player {
+        Player {
+            board: Board::new(),
+            level: 1,
+            score: 0,
+            lives,
+            bonus_life_awarded: false,
+        }
+    }
+
+    // the arcade only ever awards one bonus life.
+    // Returns true if these points earned it.
+    pub fn add_score(&mut self, points: u32, bonus_life: Option<u32>) -> bool {
+        self.score += points;
+        if let Some(threshold) = bonus_life {
+            if !self.bonus_life_awarded && self.score >= threshold {
+                self.bonus_life_awarded = true;
+                self.lives += 1;
+                return true;
+            }
+        }
+        false
+    }
+
+    // fresh maze for the next level, score and lives carry over
+    pub fn advance_level(&mut self, maze: &Board) {
+        self.level = self.level.saturating_add(1);
+        self.board = maze.clone();
+    }
+}
+
+#[derive(Clone)]
+pub struct Players {
+    players: Vec<Player>,
+    current: usize,
+}
+
+impl Players {
+    // every player starts out as `start`
+    pub fn new(num_players: usize, start: Player) -> Players {
+        assert!(num_players == 1 || num_players == 2);
+        Players {
+            players: vec![start; num_players],
+            current: 0,
+        }
+    }
+
+    // for restoring saved games
+    pub fn from_parts(players: Vec<Player>, current: usize) -> Players {
+        assert!(players.len() == 1 || players.len() == 2);
+        assert!(current < players.len());
+        Players { players, current }
+    }
+
+    pub fn num_players(&self) -> usize {
+        self.players.len()
+    }
+
+    pub fn current_index(&self) -> usize {
+        self.current
+    }
+
+    pub fn current(&self) -> &Player {
+        &self.players[self.current]
+    }
+
+    pub fn current_mut(&mut self) -> &mut Player {
+        &mut self.players[self.current]
+    }
+
+    pub fn get(&self, index: usize) -> &Player {
+        &self.players[index]
+    }
+
+    // The current player loses a life and, like the arcade, play passes to the
+    // next player who still has lives left. A player who is the only one left
+    // keeps playing.
+    // Returns `true` if the player changed.
+    pub fn on_death(&mut self) -> bool {
+        let player = &mut self.players[self.current];
+        player.lives = player.lives.saturating_sub(1);
+
+        let previous = self.current;
+        let num_players = self.players.len();
+        for offset in 1..=num_players {
+            let next = (previous + offset) % num_players;
+            if self.players[next].lives > 0 {
+                self.current = next;
+                break;
+            }
+        }
+        self.current != previous
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        self.players.iter().all(|p| p.lives == 0)
+    }
+}
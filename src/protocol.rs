@@ -0,0 +1,437 @@
+// A line based JSON protocol for driving the game from another program.
+//
+// Each line in is one command object, each line out one reply object. Replies
+// have a "type": "ok", "error", "state" or "tick".
+//
+//   {"cmd": "input", "input": "left"}      hold a direction (up, down, left,
+//                                           right or none) for the next ticks
+//   {"cmd": "step", "ticks": 10}            play ticks (default 1), one "tick"
+//                                           reply each with its events and the
+//                                           state after it, sent as it's played.
+//                                           Can also take an "input" to hold
+//                                           from now on.
+//   {"cmd": "state"}                        the state, with the maze as rows in
+//                                           the maze file format
+//   {"cmd": "reset", "seed": 7}             a new game, set up like the first
+//   {"cmd": "load_state", "path": "..."}   replace the game with a save state
+//   {"cmd": "save_state", "path": "..."}
+//                                           (both need the arcade maze)
+//   {"cmd": "teleport", "actor": "inky", "tile": [6, 5]}
+//                                           move Pac-Man or a ghost
+//   {"cmd": "set_mode", "mode": "chase"}    scatter, chase or frightened
+//
+// Positions are in pixels from the top left of the maze, tiles are [x, y].
+// A session that only uses `input` and `step` plays out exactly like a replay
+// of the same inputs would; the other commands that change the game (reset,
+// load_state, teleport, set_mode) leave that behind.
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::board::{BoardPos, Dir};
+use crate::event::GameEvent;
+use crate::game::{Game, GhostKind, GhostMode, GhostState, Phase};
+use crate::input::GameInput;
+use crate::savestate;
+
+// Where a session's replies go
+pub type Reply<'a> = dyn FnMut(Value) -> Result<(), String> + 'a;
+
+pub struct Session<'a> {
+    pub game: Game,
+    // held for every tick until the next "input"
+    pub input: GameInput,
+    // sets up the game for a seed, for "reset"
+    new_game: Box<dyn Fn(u64) -> Game + 'a>,
+}
+
+impl<'a> Session<'a> {
+    // `new_game` sets a game up for a seed, the same way every time: rules,
+    // maze, scenario, ghost AIs
+    pub fn new(new_game: impl Fn(u64) -> Game + 'a, seed: u64) -> Session<'a> {
+        Session {
+            game: new_game(seed),
+            input: GameInput::Nil,
+            new_game: Box::new(new_game),
+        }
+    }
+
+    // Run one line of input, passing each reply to `reply` as soon as it's
+    // ready. A failed command gets an "error" reply, so every line gets at
+    // least one. Errors are `reply`'s own.
+    pub fn handle_line(&mut self, line: &str, reply: &mut Reply) -> Result<(), String> {
+        let result = match serde_json::from_str::<Value>(line) {
+            Ok(command) => self.handle(&command, reply),
+            Err(e) => Err(format!("bad json: {}", e)),
+        };
+        match result {
+            Ok(()) => Ok(()),
+            // if `reply` is what failed this fails the same way
+            Err(e) => reply(error(&e)),
+        }
+    }
+
+    pub fn handle(&mut self, command: &Value, reply: &mut Reply) -> Result<(), String> {
+        let cmd = command
+            .get("cmd")
+            .and_then(Value::as_str)
+            .ok_or("commands need a \"cmd\"")?;
+        match cmd {
+            "input" => {
+                self.input = input_field(command)?.ok_or("input needs an \"input\"")?;
+                reply(ok())
+            }
+            "step" => {
+                if let Some(input) = input_field(command)? {
+                    self.input = input;
+                }
+                let ticks = match command.get("ticks") {
+                    None => 1,
+                    Some(n) => n
+                        .as_u64()
+                        .filter(|&n| n > 0)
+                        .ok_or("\"ticks\" must be a whole number from 1")?,
+                };
+                if self.game.is_game_over() {
+                    return Err("the game is over, \"reset\" starts a new one".to_string());
+                }
+                for _ in 0..ticks {
+                    if self.game.is_game_over() {
+                        break;
+                    }
+                    self.game.tick(self.input);
+                    reply(tick_json(&self.game))?;
+                }
+                Ok(())
+            }
+            "reset" => {
+                let seed = command.get("seed").and_then(Value::as_u64).unwrap_or(0);
+                let trace_decisions = self.game.trace_decisions;
+                self.game = (self.new_game)(seed);
+                self.game.trace_decisions = trace_decisions;
+                self.input = GameInput::Nil;
+                reply(ok())
+            }
+            _ => {
+                let replies = game_command(&mut self.game, command)
+                    .unwrap_or_else(|| Err(format!("unknown command `{}`", cmd)))?;
+                replies.into_iter().try_for_each(reply)
+            }
+        }
+    }
+}
+
+// The commands that only need the game: state, load_state, save_state,
+// teleport and set_mode. None if `command` isn't one of them.
+pub fn game_command(game: &mut Game, command: &Value) -> Option<Result<Vec<Value>, String>> {
+    let cmd = command.get("cmd").and_then(Value::as_str)?;
+    let result = match cmd {
+        "state" => {
+            let mut state = state_json(game);
+            state["type"] = json!("state");
+            state["maze"] = json!(game.board().rows());
+            Ok(vec![state])
+        }
+        "load_state" | "save_state" if !savestate::supports(game) => {
+            Err("save states only work with the arcade maze".to_string())
+        }
+        "load_state" => path_field(command)
+            .and_then(|path| savestate::load(Path::new(path)))
+            .map(|loaded| {
+                *game = loaded;
+                vec![ok()]
+            }),
+        "save_state" => path_field(command)
+            .and_then(|path| savestate::save(game, Path::new(path)))
+            .map(|_| vec![ok()]),
+        "teleport" => teleport(game, command).map(|_| vec![ok()]),
+        "set_mode" => set_mode(game, command).map(|_| vec![ok()]),
+        _ => return None,
+    };
+    Some(result)
+}
+
+fn set_mode(game: &mut Game, command: &Value) -> Result<(), String> {
+    match command.get("mode").and_then(Value::as_str) {
+        Some("scatter") => game.set_mode(GhostMode::Scatter),
+        Some("chase") => game.set_mode(GhostMode::Chase),
+        Some("frightened") => game.frighten(),
+        _ => return Err("\"mode\" must be scatter, chase or frightened".to_string()),
+    }
+    Ok(())
+}
+
+fn teleport(game: &mut Game, command: &Value) -> Result<(), String> {
+    let tile = command
+        .get("tile")
+        .and_then(Value::as_array)
+        .filter(|t| t.len() == 2)
+        .and_then(|t| Some((t[0].as_u64()?, t[1].as_u64()?)))
+        .map(|(x, y)| BoardPos {
+            x: x as usize,
+            y: y as usize,
+        })
+        .ok_or("\"tile\" must be [x, y]")?;
+    let actor = command
+        .get("actor")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    if actor == "pacman" {
+        return game.teleport_pacman(tile);
+    }
+    match GhostKind::ALL.iter().find(|&&kind| name_of(kind) == actor) {
+        Some(&kind) => game.teleport_ghost(kind, tile),
+        None => Err(format!("unknown actor `{}`", actor)),
+    }
+}
+
+// --stdio: run commands from stdin until it closes
+pub fn run_stdio(new_game: impl Fn(u64) -> Game, seed: u64) -> Result<(), String> {
+    let mut session = Session::new(new_game, seed);
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    for line in stdin.lock().lines() {
+        let line = line.map_err(|e| format!("could not read stdin: {}", e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        session.handle_line(&line, &mut |reply| {
+            writeln!(out, "{}", reply)
+                .and_then(|_| out.flush())
+                .map_err(|e| format!("could not write stdout: {}", e))
+        })?;
+    }
+    Ok(())
+}
+
+pub fn state_json(game: &Game) -> Value {
+    let player = game.players.current();
+    let ghosts: Vec<Value> = game
+        .ghosts
+        .iter()
+        .map(|g| {
+            json!({
+                "kind": name_of(g.kind),
+                "x": g.pos.x.to_f32(),
+                "y": g.pos.y.to_f32(),
+                "tile": tile_json(game.ghost_tile(g.kind)),
+                "dir": dir_name(g.dir),
+                "state": match g.state {
+                    GhostState::InHouse => "in_house",
+                    GhostState::LeavingHouse => "leaving_house",
+                    GhostState::Active => "active",
+                    GhostState::Eaten => "eaten",
+                    GhostState::EnteringHouse => "entering_house",
+                },
+                "frightened": g.frightened,
+                "target": game.ghost_target(g.kind).map(|(x, y)| json!([x, y])),
+            })
+        })
+        .collect();
+    let (phase, phase_ticks) = match game.phase {
+        Phase::Ready(t) => ("ready", Some(t)),
+        Phase::Playing => ("playing", None),
+        Phase::Dying(t) => ("dying", Some(t)),
+        Phase::LevelComplete(t) => ("level_complete", Some(t)),
+        Phase::Intermission(_) => ("intermission", None),
+        Phase::GameOver => ("game_over", None),
+    };
+    json!({
+        "tick": game.ticks,
+        "phase": phase,
+        "phase_ticks": phase_ticks,
+        "player": game.players.current_index(),
+        "score": player.score,
+        "lives": player.lives,
+        "level": player.level,
+        "pellets_left": game.board().num_pellets_remaining(),
+        "mode": name_of(game.mode),
+        "fright_ticks": game.fright_ticks,
+        "pacman": {
+            "x": game.pacman.pos.x.to_f32(),
+            "y": game.pacman.pos.y.to_f32(),
+            "tile": tile_json(game.pacman_tile()),
+            "dir": dir_name(game.pacman.dir),
+            "wanted_dir": dir_name(game.pacman.wanted_dir),
+        },
+        "ghosts": ghosts,
+        "fruit": game.bonus_fruit.map(|f| json!({
+            "fruit": name_of(f.fruit),
+            "ticks_left": f.ticks_left,
+        })),
+    })
+}
+
+pub fn event_json(event: &GameEvent) -> Value {
+    match *event {
+        GameEvent::PelletEaten { tile } => {
+            json!({"event": "pellet_eaten", "tile": tile_json(tile)})
+        }
+        GameEvent::EnergizerEaten { tile } => {
+            json!({"event": "energizer_eaten", "tile": tile_json(tile)})
+        }
+        GameEvent::GhostEaten { ghost, points } => {
+            json!({"event": "ghost_eaten", "ghost": name_of(ghost), "points": points})
+        }
+        GameEvent::PacmanDied { ghost } => {
+            json!({"event": "pacman_died", "ghost": name_of(ghost)})
+        }
+        GameEvent::FruitSpawned { fruit } => {
+            json!({"event": "fruit_spawned", "fruit": name_of(fruit)})
+        }
+        GameEvent::FruitEaten { fruit, points } => {
+            json!({"event": "fruit_eaten", "fruit": name_of(fruit), "points": points})
+        }
+        GameEvent::ExtraLife => json!({"event": "extra_life"}),
+        GameEvent::LevelCleared { level } => json!({"event": "level_cleared", "level": level}),
+        GameEvent::ModeChanged { mode } => json!({"event": "mode_changed", "mode": name_of(mode)}),
+        GameEvent::FrightEnded => json!({"event": "fright_ended"}),
+    }
+}
+
+// The reply to each tick of a "step"
+pub fn tick_json(game: &Game) -> Value {
+    let events: Vec<Value> = game.events().iter().map(event_json).collect();
+    json!({"type": "tick", "events": events, "state": state_json(game)})
+}
+
+pub fn input_from_name(name: &str) -> Option<GameInput> {
+    match name {
+        "up" => Some(GameInput::Up),
+        "down" => Some(GameInput::Down),
+        "left" => Some(GameInput::Left),
+        "right" => Some(GameInput::Right),
+        "none" => Some(GameInput::Nil),
+        _ => None,
+    }
+}
+
+pub fn dir_name(dir: Dir) -> &'static str {
+    match dir {
+        Dir::Up => "up",
+        Dir::Left => "left",
+        Dir::Down => "down",
+        Dir::Right => "right",
+    }
+}
+
+pub fn ok() -> Value {
+    json!({"type": "ok"})
+}
+
+pub fn error(message: &str) -> Value {
+    json!({"type": "error", "message": message})
+}
+
+// ghost kinds, modes and fruit are all single words
+fn name_of<T: std::fmt::Debug>(value: T) -> String {
+    format!("{:?}", value).to_lowercase()
+}
+
+fn tile_json(tile: BoardPos) -> Value {
+    json!([tile.x, tile.y])
+}
+
+fn input_field(command: &Value) -> Result<Option<GameInput>, String> {
+    match command.get("input") {
+        None => Ok(None),
+        Some(v) => {
+            let name = v.as_str().unwrap_or_default();
+            input_from_name(name)
+                .map(Some)
+                .ok_or_else(|| format!("unknown input {}", v))
+        }
+    }
+}
+
+fn path_field(command: &Value) -> Result<&str, String> {
+    command
+        .get("path")
+        .and_then(Value::as_str)
+        .ok_or_else(|| "this command needs a \"path\"".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::{state_json, Session};
+    use crate::config::Config;
+    use crate::game::Game;
+    use crate::scenario::Scenario;
+
+    fn replies(session: &mut Session, line: &str) -> Vec<Value> {
+        let mut replies = Vec::new();
+        session
+            .handle_line(line, &mut |reply| {
+                replies.push(reply);
+                Ok(())
+            })
+            .unwrap();
+        replies
+    }
+
+    #[test]
+    fn step_always_replies() {
+        let mut session = Session::new(|seed| Game::new(Config::default(), 1, seed), 3);
+        let ticks = replies(&mut session, r#"{"cmd": "step", "ticks": 3}"#);
+        assert_eq!(ticks.len(), 3);
+        assert!(ticks.iter().all(|t| t["type"] == "tick"));
+        assert_eq!(ticks[2]["state"]["tick"], 3);
+
+        let zero = replies(&mut session, r#"{"cmd": "step", "ticks": 0}"#);
+        assert_eq!(zero.len(), 1);
+        assert_eq!(zero[0]["type"], "error");
+
+        while !session.game.is_game_over() {
+            session.game.tick(session.input);
+        }
+        let over = replies(&mut session, r#"{"cmd": "step"}"#);
+        assert_eq!(over.len(), 1);
+        assert_eq!(over[0]["type"], "error");
+    }
+
+    #[test]
+    fn step_stops_when_replies_cant_be_sent() {
+        let mut session = Session::new(|seed| Game::new(Config::default(), 1, seed), 3);
+        let mut sent = 0;
+        let result = session.handle_line(r#"{"cmd": "step", "ticks": 1000000}"#, &mut |_| {
+            sent += 1;
+            if sent > 5 {
+                Err("closed".to_string())
+            } else {
+                Ok(())
+            }
+        });
+        assert_eq!(result, Err("closed".to_string()));
+        assert_eq!(session.game.ticks, 6);
+    }
+
+    #[test]
+    fn reset_sets_up_like_the_first_game() {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("scenarios")
+            .join("inky_targeting.txt");
+        let scenario = Scenario::load(&path).unwrap();
+        let new_game = |seed| {
+            let mut game = Game::new(Config::default(), 1, seed);
+            scenario.apply(&mut game).unwrap();
+            game
+        };
+        let mut session = Session::new(new_game, 3);
+        session.game.trace_decisions = true;
+        replies(&mut session, r#"{"cmd": "step", "ticks": 30}"#);
+
+        let reset = replies(&mut session, r#"{"cmd": "reset", "seed": 7}"#);
+        assert_eq!(reset[0]["type"], "ok");
+        let state = replies(&mut session, r#"{"cmd": "state"}"#).remove(0);
+        let fresh = new_game(7);
+        let mut expected = state_json(&fresh);
+        expected["type"] = "state".into();
+        expected["maze"] = fresh.board().rows().into();
+        assert_eq!(state, expected);
+        assert!(session.game.trace_decisions);
+    }
+}
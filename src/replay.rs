@@ -0,0 +1,321 @@
+// Input recordings that reproduce a game exactly.
+//
+// The simulation is deterministic, so a game is fully described by the rules
+// it was played with, its RNG seed and the `GameInput` of every tick. The
+// state hashes and save states are only there to check that (see
+// `desync.rs`).
+//
+// File layout, little endian:
+//   magic        b"PMRP"
+//   version      u16
+//   seed         u64
+//   config       see `Config::write`
+//   num_players  u8
+//   num_ticks    u32
+//   inputs       runs of (input u8, count u16) covering num_ticks
+//   hashes       u64 per tick, of the state after it
+//   num_states   u32
+//   states       (tick u32, length u32, save state), every KEYFRAME_TICKS
+//                and after the last tick
+use std::convert::TryFrom;
+
+use crate::codec::{Reader, Writer};
+use crate::config::Config;
+use crate::desync::{self, Desync};
+use crate::game::{Game, TICKS_PER_SECOND};
+use crate::headless::InputSource;
+use crate::input::GameInput;
+use crate::savestate;
+
+const MAGIC: &[u8; 4] = b"PMRP";
+pub const REPLAY_VERSION: u16 = 4;
+// how often a full save state is kept, they're a couple of KB each
+pub const KEYFRAME_TICKS: u64 = 10 * TICKS_PER_SECOND as u64;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    pub config: Config,
+    pub num_players: usize,
+    // one per tick
+    pub inputs: Vec<GameInput>,
+    // `desync::state_hash` after each tick
+    pub hashes: Vec<u64>,
+    // (tick, save state after it), oldest first
+    pub keyframes: Vec<(u64, Vec<u8>)>,
+}
+
+impl Replay {
+    pub fn new(config: Config, num_players: usize, seed: u64) -> Replay {
+        Replay {
+            seed,
+            config,
+            num_players,
+            inputs: Vec::new(),
+            hashes: Vec::new(),
+            keyframes: Vec::new(),
+        }
+    }
+
+    // Call after each tick with its input and the game it left
+    pub fn record(&mut self, input: GameInput, game: &Game) {
+        self.inputs.push(input);
+        let tick = self.inputs.len() as u64;
+        let state = savestate::to_bytes(game);
+        self.hashes.push(desync::fnv1a(&state));
+        // the latest state is always kept so the end has one too
+        if let Some(&(last, _)) = self.keyframes.last() {
+            if last % KEYFRAME_TICKS != 0 {
+                self.keyframes.pop();
+            }
+        }
+        self.keyframes.push((tick, state));
+    }
+
+    // The game as it was before the first recorded tick
+    pub fn new_game(&self) -> Game {
+        Game::new(self.config, self.num_players, self.seed)
+    }
+
+    pub fn playback(&self) -> ReplayPlayback {
+        ReplayPlayback {
+            inputs: self.inputs.clone(),
+            hashes: self.hashes.clone(),
+            pos: 0,
+            desynced: false,
+        }
+    }
+
+    // Play the replay through and check every tick comes out as recorded
+    pub fn verify(&self) -> Result<(), Desync> {
+        let mut game = self.new_game();
+        let mut first_desync = None;
+        for (i, &input) in self.inputs.iter().enumerate() {
+            game.tick(input);
+            let tick = i as u64 + 1;
+            if first_desync.is_none() && desync::state_hash(&game) != self.hashes[i] {
+                first_desync = Some(tick);
+            }
+            if let Some(desync_tick) = first_desync {
+                if let Some(expected) = self.keyframe(tick) {
+                    return Err(Desync {
+                        tick: desync_tick,
+                        compared_tick: Some(tick),
+                        diff: desync::diff(&expected, &game),
+                    });
+                }
+            }
+        }
+        match first_desync {
+            Some(tick) => Err(Desync {
+                tick,
+                compared_tick: None,
+                diff: Vec::new(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    // The recorded game after `tick` ticks, if a save state was kept for it
+    fn keyframe(&self, tick: u64) -> Option<Game> {
+        let (_, state) = self.keyframes.iter().find(|(t, _)| *t == tick)?;
+        savestate::from_bytes(state).ok()
+    }
+
+    // Fails if the replay is too long for the format's u32 counts
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        let mut w = Writer::new();
+        w.raw(MAGIC);
+        w.u16(REPLAY_VERSION);
+        w.u64(self.seed);
+        self.config.write(&mut w);
+        w.u8(self.num_players as u8);
+        w.u32(fits_u32(self.inputs.len() as u64, "ticks")?);
+
+        let mut i = 0;
+        while i < self.inputs.len() {
+            let input = self.inputs[i];
+            let mut count = 1;
+            while i + count < self.inputs.len()
+                && self.inputs[i + count] == input
+                && count < u16::MAX as usize
+            {
+                count += 1;
+            }
+            w.u8(input.to_u8());
+            w.u16(count as u16);
+            i += count;
+        }
+
+        assert_eq!(self.hashes.len(), self.inputs.len());
+        for &hash in self.hashes.iter() {
+            w.u64(hash);
+        }
+        w.u32(fits_u32(self.keyframes.len() as u64, "keyframes")?);
+        for (tick, state) in self.keyframes.iter() {
+            w.u32(fits_u32(*tick, "ticks")?);
+            w.u32(fits_u32(state.len() as u64, "save state bytes")?);
+            w.raw(state);
+        }
+        Ok(w.bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Replay, String> {
+        let mut r = Reader::new(bytes);
+        if r.raw(MAGIC.len())? != MAGIC {
+            return Err("not a replay file".to_string());
+        }
+        let version = r.u16()?;
+        if version != REPLAY_VERSION {
+            return Err(format!(
+                "replay version {} is not supported (expected {})",
+                version, REPLAY_VERSION
+            ));
+        }
+        let seed = r.u64()?;
+        let config = Config::read(&mut r)?;
+        let num_players = r.u8()? as usize;
+        if num_players != 1 && num_players != 2 {
+            return Err(format!("bad number of players {}", num_players));
+        }
+        let num_ticks = r.u32()? as usize;
+        // every tick has an 8 byte hash after the inputs, so a count the
+        // file can't hold is corrupt, and mustn't size the allocations below
+        if num_ticks.saturating_mul(8) > r.remaining() {
+            return Err(format!("{} ticks don't fit in the replay", num_ticks));
+        }
+
+        let mut inputs = Vec::with_capacity(num_ticks);
+        while inputs.len() < num_ticks {
+            let v = r.u8()?;
+            let input = GameInput::from_u8(v).ok_or_else(|| format!("bad input {}", v))?;
+            let count = r.u16()? as usize;
+            inputs.extend(std::iter::repeat_n(input, count));
+        }
+        if inputs.len() != num_ticks {
+            return Err("replay inputs don't match the tick count".to_string());
+        }
+
+        let mut hashes = Vec::with_capacity(num_ticks);
+        for _ in 0..num_ticks {
+            hashes.push(r.u64()?);
+        }
+        let num_keyframes = r.u32()?;
+        let mut keyframes = Vec::new();
+        for _ in 0..num_keyframes {
+            let tick = r.u32()? as u64;
+            let len = r.u32()? as usize;
+            keyframes.push((tick, r.raw(len)?.to_vec()));
+        }
+        if !r.is_empty() {
+            return Err("trailing data after replay".to_string());
+        }
+
+        Ok(Replay {
+            seed,
+            config,
+            num_players,
+            inputs,
+            hashes,
+            keyframes,
+        })
+    }
+
+    pub fn save(&self, path: &std::path::Path) -> Result<(), String> {
+        std::fs::write(path, self.to_bytes()?)
+            .map_err(|e| format!("could not write {}: {}", path.display(), e))
+    }
+
+    pub fn load(path: &std::path::Path) -> Result<Replay, String> {
+        let bytes =
+            std::fs::read(path).map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+        Replay::from_bytes(&bytes).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+// Feeds a replay's inputs back one tick at a time
+pub struct ReplayPlayback {
+    inputs: Vec<GameInput>,
+    hashes: Vec<u64>,
+    pub pos: usize,
+    desynced: bool,
+}
+
+impl ReplayPlayback {
+    pub fn is_finished(&self) -> bool {
+        self.pos >= self.inputs.len()
+    }
+
+    pub fn take_input(&mut self) -> GameInput {
+        let input = self.inputs.get(self.pos).cloned().unwrap_or(GameInput::Nil);
+        self.pos += 1;
+        input
+    }
+
+    // Call after ticking with the last input. Returns the tick number the
+    // first time the game doesn't match the recording, after that there's
+    // no point checking.
+    pub fn check(&mut self, game: &Game) -> Option<u64> {
+        if self.desynced || self.pos == 0 {
+            return None;
+        }
+        let expected = *self.hashes.get(self.pos - 1)?;
+        if desync::state_hash(game) != expected {
+            self.desynced = true;
+            return Some(self.pos as u64);
+        }
+        None
+    }
+}
+
+impl InputSource for ReplayPlayback {
+    fn next_input(&mut self, _game: &Game) -> GameInput {
+        self.take_input()
+    }
+}
+
+fn fits_u32(n: u64, what: &str) -> Result<u32, String> {
+    u32::try_from(n).map_err(|_| format!("too many {} for a replay: {}", what, n))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Replay, KEYFRAME_TICKS};
+    use crate::bot::Autopilot;
+    use crate::config::Config;
+
+    // a seeded autopilot game a bit past its second keyframe
+    fn recorded() -> Replay {
+        let mut replay = Replay::new(Config::default(), 1, 42);
+        let mut game = replay.new_game();
+        let autopilot = Autopilot::new();
+        for _ in 0..2 * KEYFRAME_TICKS + 100 {
+            let input = autopilot.choose_input(&game);
+            game.tick(input);
+            replay.record(input, &game);
+        }
+        replay
+    }
+
+    #[test]
+    fn round_trips_and_verifies() {
+        let replay = recorded();
+        let bytes = replay.to_bytes().unwrap();
+        let loaded = Replay::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded, replay);
+        assert_eq!(loaded.to_bytes().unwrap(), bytes);
+        assert_eq!(loaded.verify(), Ok(()));
+    }
+
+    #[test]
+    fn reports_a_corrupt_hash_at_its_tick() {
+        let mut replay = recorded();
+        let tick = KEYFRAME_TICKS + 100;
+        replay.hashes[tick as usize - 1] ^= 1;
+        let desync = replay.verify().unwrap_err();
+        assert_eq!(desync.tick, tick);
+        // the game itself never went wrong, so the next keyframe matches
+        assert_eq!(desync.compared_tick, Some(2 * KEYFRAME_TICKS));
+        assert!(desync.diff.is_empty());
+    }
+}
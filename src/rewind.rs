@@ -0,0 +1,189 @@
+// Rewind: step the game backwards one tick at a time.
+//
+// Storing every tick would be wasteful, so this keeps a full copy of the game
+// every `keyframe_interval` ticks plus the inputs in between. Going back a
+// tick re-simulates from the nearest keyframe, which works because the
+// simulation is deterministic. The oldest keyframes are dropped to stay under
+// the memory budget.
+use std::collections::VecDeque;
+
+use crate::game::Game;
+use crate::input::GameInput;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RewindConfig {
+    pub memory_budget_bytes: usize,
+    // ticks between full copies of the game, higher is smaller but slower to rewind
+    pub keyframe_interval: usize,
+}
+
+impl Default for RewindConfig {
+    fn default() -> RewindConfig {
+        RewindConfig {
+            memory_budget_bytes: 16 * 1024 * 1024,
+            keyframe_interval: 60,
+        }
+    }
+}
+
+struct Keyframe {
+    game: Game,
+    // inputs of the ticks played from `game` onwards
+    inputs: Vec<GameInput>,
+}
+
+pub struct RewindBuffer {
+    pub config: RewindConfig,
+    keyframes: VecDeque<Keyframe>,
+    memory_used: usize,
+}
+
+impl RewindBuffer {
+    pub fn new(config: RewindConfig) -> RewindBuffer {
+        assert!(config.keyframe_interval > 0);
+        RewindBuffer {
+            config,
+            keyframes: VecDeque::new(),
+            memory_used: 0,
+        }
+    }
+
+    // Call right before `game.tick(input)`
+    pub fn record(&mut self, game: &Game, input: GameInput) {
+        let need_keyframe = match self.keyframes.back() {
+            None => true,
+            Some(keyframe) => keyframe.inputs.len() >= self.config.keyframe_interval,
+        };
+        if need_keyframe {
+            self.memory_used += approx_game_size(game);
+            self.keyframes.push_back(Keyframe {
+                game: game.clone(),
+                inputs: Vec::with_capacity(self.config.keyframe_interval),
+            });
+        }
+        self.keyframes.back_mut().unwrap().inputs.push(input);
+        self.memory_used += std::mem::size_of::<GameInput>();
+
+        // always keep the newest keyframe, even if it alone is over budget
+        while self.memory_used > self.config.memory_budget_bytes && self.keyframes.len() > 1 {
+            let oldest = self.keyframes.pop_front().unwrap();
+            self.memory_used -= keyframe_size(&oldest);
+        }
+    }
+
+    // The game as it was one tick before the last recorded tick.
+    // None once there's no history left.
+    pub fn rewind(&mut self) -> Option<Game> {
+        // a keyframe with no inputs is the current state, go past it
+        if self.keyframes.back()?.inputs.is_empty() {
+            let current = self.keyframes.pop_back().unwrap();
+            self.memory_used -= keyframe_size(&current);
+        }
+
+        let keyframe = self.keyframes.back_mut()?;
+        keyframe.inputs.pop();
+        self.memory_used -= std::mem::size_of::<GameInput>();
+
+        let mut game = keyframe.game.clone();
+        for &input in keyframe.inputs.iter() {
+            game.tick(input);
+        }
+        Some(game)
+    }
+
+    pub fn ticks_available(&self) -> usize {
+        self.keyframes.iter().map(|k| k.inputs.len()).sum()
+    }
+
+    pub fn memory_used(&self) -> usize {
+        self.memory_used
+    }
+
+    pub fn clear(&mut self) {
+        self.keyframes.clear();
+        self.memory_used = 0;
+    }
+}
+
+fn keyframe_size(keyframe: &Keyframe) -> usize {
+    approx_game_size(&keyframe.game) + keyframe.inputs.len() * std::mem::size_of::<GameInput>()
+}
+
+// The game plus the heap allocated boards
+fn approx_game_size(game: &Game) -> usize {
+    let board = game.board();
+    let vecs_per_board = 5;
+    let board_bytes = vecs_per_board * board.num_tiles * std::mem::size_of::<bool>();
+    std::mem::size_of::<Game>() + game.players.num_players() * board_bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{approx_game_size, RewindBuffer, RewindConfig};
+    use crate::bot::Autopilot;
+    use crate::config::Config;
+    use crate::game::Game;
+    use crate::savestate;
+
+    fn new_game() -> Game {
+        Game::new(Config::default(), 1, 5)
+    }
+
+    // the autopilot game after `ticks` ticks, as save state bytes
+    fn fresh_run(ticks: usize) -> Vec<u8> {
+        let autopilot = Autopilot::new();
+        let mut game = new_game();
+        for _ in 0..ticks {
+            game.tick(autopilot.choose_input(&game));
+        }
+        savestate::to_bytes(&game)
+    }
+
+    // plays `ticks` ticks recording each, returns the buffer
+    fn recorded(config: RewindConfig, ticks: usize) -> RewindBuffer {
+        let autopilot = Autopilot::new();
+        let mut buffer = RewindBuffer::new(config);
+        let mut game = new_game();
+        for _ in 0..ticks {
+            let input = autopilot.choose_input(&game);
+            buffer.record(&game, input);
+            game.tick(input);
+        }
+        buffer
+    }
+
+    #[test]
+    fn rewinds_to_the_same_game_as_a_fresh_run() {
+        let mut buffer = recorded(RewindConfig::default(), 300);
+        assert_eq!(buffer.ticks_available(), 300);
+        // across a keyframe boundary and back to the start
+        for tick in (0..300).rev() {
+            let game = buffer.rewind().unwrap();
+            if tick % 50 == 0 || tick == 239 || tick == 240 {
+                assert_eq!(savestate::to_bytes(&game), fresh_run(tick), "tick {}", tick);
+            }
+        }
+        assert!(buffer.rewind().is_none());
+    }
+
+    #[test]
+    fn drops_old_keyframes_over_budget() {
+        let config = RewindConfig {
+            memory_budget_bytes: 3 * approx_game_size(&new_game()) + 1000,
+            keyframe_interval: 60,
+        };
+        let mut buffer = recorded(config, 600);
+        assert!(buffer.memory_used() <= config.memory_budget_bytes);
+        let available = buffer.ticks_available();
+        assert!((120..600).contains(&available), "{} ticks", available);
+
+        // what's left still rewinds correctly, as far back as it goes
+        let mut game = None;
+        for _ in 0..available {
+            game = buffer.rewind();
+        }
+        let oldest = 600 - available;
+        assert_eq!(savestate::to_bytes(&game.unwrap()), fresh_run(oldest));
+        assert!(buffer.rewind().is_none());
+    }
+}
@@ -0,0 +1,36 @@
+// Small seedable PRNG (xorshift64*) for the simulation.
+// The whole state is one u64 so games can be replayed from a seed and the
+// state can be copied around with the rest of the game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rng {
+    pub state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // splitmix64 the seed so nearby seeds don't start out correlated,
+        // and xorshift must never be seeded with 0
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        Rng {
+            state: if z == 0 { 1 } else { z },
+        }
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+        (x.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 32) as u32
+    }
+
+    // in [0, n)
+    pub fn below(&mut self, n: u32) -> u32 {
+        assert!(n > 0);
+        self.next_u32() % n
+    }
+}
@@ -0,0 +1,336 @@
+// Complete snapshots of a `Game`, for jumping straight back to a situation.
+//
+// Unlike a replay this doesn't need the game's history, everything the next
+// tick depends on is in here: pellets, actors, timers, RNG state and scores.
+// Walls don't change so only the pellets of each board are saved, which means
+// save states always use the arcade maze. Ghost AIs aren't saved either, a
+// loaded game has the arcade's.
+//
+// File layout, little endian:
+//   magic    b"PMSS"
+//   version  u16
+//   the game, in the order `write_game` writes it
+use crate::board::{Board, Dir};
+use crate::codec::{Reader, Writer};
+use crate::config::Config;
+use crate::cutscene::{Cutscene, Intermission};
+use crate::game::{BonusFruit, Game, Ghost, GhostKind, GhostMode, GhostState, Pacman, Phase};
+use crate::level::Fruit;
+use crate::player::{Player, Players};
+use crate::rng::Rng;
+use crate::vec2::{Fixed, Vec2};
+
+const MAGIC: &[u8; 4] = b"PMSS";
+pub const SAVE_STATE_VERSION: u16 = 3;
+
+// Whether `game` is on the arcade maze, the only one save states can bring back
+pub fn supports(game: &Game) -> bool {
+    game.maze.rows() == Board::new().rows()
+}
+
+pub fn to_bytes(game: &Game) -> Vec<u8> {
+    let mut w = Writer::new();
+    w.raw(MAGIC);
+    w.u16(SAVE_STATE_VERSION);
+    write_game(&mut w, game);
+    w.bytes
+}
+
+pub fn from_bytes(bytes: &[u8]) -> Result<Game, String> {
+    let mut r = Reader::new(bytes);
+    if r.raw(MAGIC.len())? != MAGIC {
+        return Err("not a save state".to_string());
+    }
+    let version = r.u16()?;
+    if version != SAVE_STATE_VERSION {
+        return Err(format!(
+            "save state version {} is not supported (expected {})",
+            version, SAVE_STATE_VERSION
+        ));
+    }
+    let game = read_game(&mut r)?;
+    if !r.is_empty() {
+        return Err("trailing data after save state".to_string());
+    }
+    Ok(game)
+}
+
+pub fn save(game: &Game, path: &std::path::Path) -> Result<(), String> {
+    std::fs::write(path, to_bytes(game))
+        .map_err(|e| format!("could not write {}: {}", path.display(), e))
+}
+
+pub fn load(path: &std::path::Path) -> Result<Game, String> {
+    let bytes =
+        std::fs::read(path).map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+    from_bytes(&bytes).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn write_game(w: &mut Writer, game: &Game) {
+    game.config.write(w);
+
+    w.u8(game.players.num_players() as u8);
+    w.u8(game.players.current_index() as u8);
+    for i in 0..game.players.num_players() {
+        write_player(w, game.players.get(i));
+    }
+
+    write_vec2(w, game.pacman.pos);
+    write_dir(w, game.pacman.dir);
+    write_dir(w, game.pacman.wanted_dir);
+    for ghost in game.ghosts.iter() {
+        write_vec2(w, ghost.pos);
+        write_dir(w, ghost.dir);
+        w.u8(match ghost.state {
+            GhostState::InHouse => 0,
+            GhostState::LeavingHouse => 1,
+            GhostState::Active => 2,
+            GhostState::Eaten => 3,
+            GhostState::EnteringHouse => 4,
+        });
+        w.bool(ghost.frightened);
+    }
+
+    match game.bonus_fruit {
+        None => w.bool(false),
+        Some(bonus) => {
+            w.bool(true);
+            w.u8(bonus.fruit as u8);
+            w.u32(bonus.ticks_left);
+        }
+    }
+
+    match game.phase {
+        Phase::Ready(t) => {
+            w.u8(0);
+            w.u32(t);
+        }
+        Phase::Playing => w.u8(1),
+        Phase::Dying(t) => {
+            w.u8(2);
+            w.u32(t);
+        }
+        Phase::LevelComplete(t) => {
+            w.u8(3);
+            w.u32(t);
+        }
+        Phase::Intermission(cutscene) => {
+            w.u8(4);
+            w.u8(cutscene.intermission as u8);
+            w.u32(cutscene.ticks);
+        }
+        Phase::GameOver => w.u8(5),
+    }
+
+    w.u64(game.rng.state);
+    w.u64(game.ticks);
+    w.u8(match game.mode {
+        GhostMode::Scatter => 0,
+        GhostMode::Chase => 1,
+    });
+    w.u32(game.mode_index as u32);
+    w.u32(game.mode_ticks);
+    w.u32(game.fright_ticks);
+    w.u32(game.ghosts_eaten);
+    w.u32(game.house_pellets);
+    w.u32(game.house_idle_ticks);
+    w.bool(game.life_lost_this_level);
+}
+
+fn read_game(r: &mut Reader) -> Result<Game, String> {
+    let config = Config::read(r)?;
+
+    let num_players = r.u8()? as usize;
+    let current = r.u8()? as usize;
+    if (num_players != 1 && num_players != 2) || current >= num_players {
+        return Err(format!("bad players {} / {}", current, num_players));
+    }
+    let mut players = Vec::with_capacity(num_players);
+    for _ in 0..num_players {
+        players.push(read_player(r)?);
+    }
+
+    // start from a fresh game and fill in everything that can change
+    let mut game = Game::new(config, num_players, 0);
+    game.players = Players::from_parts(players, current);
+
+    game.pacman = Pacman {
+        pos: read_vec2(r)?,
+        dir: read_dir(r)?,
+        wanted_dir: read_dir(r)?,
+    };
+    for kind in GhostKind::ALL.iter().cloned() {
+        let pos = read_vec2(r)?;
+        let dir = read_dir(r)?;
+        let state = match r.u8()? {
+            0 => GhostState::InHouse,
+            1 => GhostState::LeavingHouse,
+            2 => GhostState::Active,
+            3 => GhostState::Eaten,
+            4 => GhostState::EnteringHouse,
+            v => return Err(format!("bad ghost state {}", v)),
+        };
+        let frightened = r.bool()?;
+        game.ghosts[kind.index()] = Ghost {
+            kind,
+            pos,
+            dir,
+            state,
+            frightened,
+        };
+    }
+
+    game.bonus_fruit = if r.bool()? {
+        let fruit = read_fruit(r)?;
+        let ticks_left = r.u32()?;
+        Some(BonusFruit { fruit, ticks_left })
+    } else {
+        None
+    };
+
+    game.phase = match r.u8()? {
+        0 => Phase::Ready(r.u32()?),
+        1 => Phase::Playing,
+        2 => Phase::Dying(r.u32()?),
+        3 => Phase::LevelComplete(r.u32()?),
+        4 => {
+            let intermission = match r.u8()? {
+                0 => Intermission::GiantPacman,
+                1 => Intermission::TornCloak,
+                2 => Intermission::PatchedCloak,
+                v => return Err(format!("bad intermission {}", v)),
+            };
+            let ticks = r.u32()?;
+            Phase::Intermission(Cutscene {
+                intermission,
+                ticks,
+            })
+        }
+        5 => Phase::GameOver,
+        v => return Err(format!("bad phase {}", v)),
+    };
+
+    game.rng = Rng { state: r.u64()? };
+    game.ticks = r.u64()?;
+    game.mode = match r.u8()? {
+        0 => GhostMode::Scatter,
+        1 => GhostMode::Chase,
+        v => return Err(format!("bad ghost mode {}", v)),
+    };
+    game.mode_index = r.u32()? as usize;
+    game.mode_ticks = r.u32()?;
+    game.fright_ticks = r.u32()?;
+    game.ghosts_eaten = r.u32()?;
+    game.house_pellets = r.u32()?;
+    game.house_idle_ticks = r.u32()?;
+    game.life_lost_this_level = r.bool()?;
+    Ok(game)
+}
+
+fn write_player(w: &mut Writer, player: &Player) {
+    w.u32(player.level);
+    w.u32(player.score);
+    w.u32(player.lives);
+    w.bool(player.bonus_life_awarded);
+    for h in 0..player.board.num_tiles {
+        w.bool(player.board.has_pellet[h]);
+        w.bool(player.board.has_power_pellet[h]);
+    }
+}
+
+fn read_player(r: &mut Reader) -> Result<Player, String> {
+    let mut player = Player::new(0);
+    player.level = r.u32()?;
+    if player.level == 0 {
+        return Err("bad level 0".to_string());
+    }
+    player.score = r.u32()?;
+    player.lives = r.u32()?;
+    player.bonus_life_awarded = r.bool()?;
+    let board: &mut Board = &mut player.board;
+    for h in 0..board.num_tiles {
+        board.has_pellet[h] = r.bool()?;
+        board.has_power_pellet[h] = r.bool()?;
+    }
+    Ok(player)
+}
+
+// in sub-pixels
+fn write_vec2(w: &mut Writer, v: Vec2) {
+    w.i32(v.x.raw());
+    w.i32(v.y.raw());
+}
+
+fn read_vec2(r: &mut Reader) -> Result<Vec2, String> {
+    Ok(Vec2::new(
+        Fixed::from_raw(r.i32()?),
+        Fixed::from_raw(r.i32()?),
+    ))
+}
+
+fn write_dir(w: &mut Writer, dir: Dir) {
+    w.u8(dir as u8);
+}
+
+fn read_dir(r: &mut Reader) -> Result<Dir, String> {
+    let v = r.u8()?;
+    Dir::ALL
+        .get(v as usize)
+        .cloned()
+        .ok_or_else(|| format!("bad direction {}", v))
+}
+
+fn read_fruit(r: &mut Reader) -> Result<Fruit, String> {
+    Ok(match r.u8()? {
+        0 => Fruit::Cherries,
+        1 => Fruit::Strawberry,
+        2 => Fruit::Peach,
+        3 => Fruit::Apple,
+        4 => Fruit::Grapes,
+        5 => Fruit::Galaxian,
+        6 => Fruit::Bell,
+        7 => Fruit::Key,
+        v => return Err(format!("bad fruit {}", v)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_bytes, to_bytes};
+    use crate::bot::Autopilot;
+    use crate::config::Config;
+    use crate::game::Game;
+
+    #[test]
+    fn loaded_game_plays_on_the_same() {
+        let autopilot = Autopilot::new();
+        let mut game = Game::new(Config::default(), 2, 7);
+        for _ in 0..500 {
+            game.tick(autopilot.choose_input(&game));
+        }
+        let bytes = to_bytes(&game);
+        let mut loaded = from_bytes(&bytes).unwrap();
+        assert_eq!(to_bytes(&loaded), bytes);
+        for _ in 0..500 {
+            game.tick(autopilot.choose_input(&game));
+            loaded.tick(autopilot.choose_input(&loaded));
+        }
+        assert_eq!(to_bytes(&loaded), to_bytes(&game));
+    }
+
+    #[test]
+    fn rejects_other_files_and_versions() {
+        let bytes = to_bytes(&Game::new(Config::default(), 1, 7));
+
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+        assert_eq!(from_bytes(&bad_magic).err().unwrap(), "not a save state");
+
+        let mut bad_version = bytes;
+        bad_version[4] = bad_version[4].wrapping_add(1);
+        assert!(from_bytes(&bad_version)
+            .err()
+            .unwrap()
+            .starts_with("save state version"));
+    }
+}
@@ -0,0 +1,378 @@
+// Scenarios: a game set up mid-play, for reproducing a situation from the
+// Dossier or a tricky case a bug report describes.
+//
+// A scenario is a text file like the cfg file, everything optional:
+//
+//   # Inky's targeting example
+//   level = 1
+//   mode = chase                # scatter or chase
+//   mode_ticks = 100            # ticks into the current mode
+//   fright_ticks = 0
+//   pacman = 16,23 left         # tile and direction
+//   blinky = 21,26 up           # ... and `frightened` or `eyes`, or `house`
+//   pinky = house
+//   pellets:
+//   XXXXXXXXXXXXXXXXXXXXXXXXXXXX
+//   X............XX............X
+//   ...
+//
+// Also: `mode_index` (the place in the level's scatter/chase schedule,
+// `mode` picks the first scatter or chase by default), `score`, `lives`,
+// `house_pellets`, `house_idle_ticks`, and `phase = ready` to start with
+// READY! instead of mid-play. Pellets are rows in the maze file's characters,
+// only `.` and `o` counting, up to a blank line. Anything left out is as it is
+// at the start of a level.
+use crate::board::{BoardPos, Dir};
+use crate::game::{tile_pos, Game, GhostKind, GhostMode, GhostState, Phase};
+use crate::level::LAST_LEVEL;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scenario {
+    pub level: Option<u32>,
+    pub score: Option<u32>,
+    pub lives: Option<u32>,
+    // start at READY! rather than mid-play
+    pub ready: bool,
+    // maze file rows, top first
+    pub pellets: Option<Vec<String>>,
+    pub pacman: Option<(BoardPos, Dir)>,
+    // indexed by `GhostKind::index`
+    pub ghosts: [Option<GhostSetup>; 4],
+    pub mode: Option<GhostMode>,
+    pub mode_index: Option<usize>,
+    pub mode_ticks: Option<u32>,
+    pub fright_ticks: Option<u32>,
+    pub house_pellets: Option<u32>,
+    pub house_idle_ticks: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GhostSetup {
+    // waiting at its spot in the house
+    House,
+    Out {
+        tile: BoardPos,
+        dir: Dir,
+        frightened: bool,
+        // just the eyes, heading home
+        eyes: bool,
+    },
+}
+
+impl Scenario {
+    pub fn load(path: &std::path::Path) -> Result<Scenario, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+        Scenario::parse(&contents).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn parse(contents: &str) -> Result<Scenario, String> {
+        let mut scenario = Scenario {
+            level: None,
+            score: None,
+            lives: None,
+            ready: false,
+            pellets: None,
+            pacman: None,
+            ghosts: [None; 4],
+            mode: None,
+            mode_index: None,
+            mode_ticks: None,
+            fright_ticks: None,
+            house_pellets: None,
+            house_idle_ticks: None,
+        };
+
+        let mut lines = contents.lines().enumerate();
+        while let Some((line_num, line)) = lines.next() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line == "pellets:" {
+                let rows = lines
+                    .by_ref()
+                    .map(|(_, row)| row.trim_end())
+                    .take_while(|row| !row.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                scenario.pellets = Some(rows);
+                continue;
+            }
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => return Err(format!("line {}: expected `key = value`", line_num + 1)),
+            };
+            scenario
+                .set(key, value)
+                .map_err(|e| format!("line {}: {}", line_num + 1, e))?;
+        }
+        Ok(scenario)
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "level" => self.level = Some(parse_number(key, value)?),
+            "score" => self.score = Some(parse_number(key, value)?),
+            "lives" => self.lives = Some(parse_number(key, value)?),
+            "phase" => {
+                self.ready = match value {
+                    "ready" => true,
+                    "playing" => false,
+                    _ => return Err(format!("phase must be ready or playing, got `{}`", value)),
+                }
+            }
+            "pacman" => {
+                let (tile, dir, rest) = parse_actor(value)?;
+                if let Some(word) = rest.first() {
+                    return Err(format!("unexpected `{}` after Pac-Man's direction", word));
+                }
+                self.pacman = Some((tile, dir));
+            }
+            "blinky" | "pinky" | "inky" | "clyde" => {
+                let kind = GhostKind::ALL[["blinky", "pinky", "inky", "clyde"]
+                    .iter()
+                    .position(|&name| name == key)
+                    .unwrap()];
+                self.ghosts[kind.index()] = Some(parse_ghost(value)?);
+            }
+            "mode" => {
+                self.mode = Some(match value {
+                    "scatter" => GhostMode::Scatter,
+                    "chase" => GhostMode::Chase,
+                    _ => return Err(format!("mode must be scatter or chase, got `{}`", value)),
+                })
+            }
+            "mode_index" => self.mode_index = Some(parse_number(key, value)?),
+            "mode_ticks" => self.mode_ticks = Some(parse_number(key, value)?),
+            "fright_ticks" => self.fright_ticks = Some(parse_number(key, value)?),
+            "house_pellets" => self.house_pellets = Some(parse_number(key, value)?),
+            "house_idle_ticks" => self.house_idle_ticks = Some(parse_number(key, value)?),
+            _ => return Err(format!("unknown setting `{}`", key)),
+        }
+        Ok(())
+    }
+
+    // Set `game` up as described, on top of however it was
+    pub fn apply(&self, game: &mut Game) -> Result<(), String> {
+        let player = game.players.current_mut();
+        if let Some(level) = self.level {
+            if !(1..=LAST_LEVEL).contains(&level) {
+                return Err(format!("levels go from 1 to {}", LAST_LEVEL));
+            }
+            player.level = level;
+        }
+        if let Some(score) = self.score {
+            player.score = score;
+        }
+        if let Some(lives) = self.lives {
+            player.lives = lives;
+        }
+
+        let board = &mut player.board;
+        if let Some(rows) = &self.pellets {
+            if rows.len() != board.height {
+                return Err(format!(
+                    "pellets: expected {} rows, got {}",
+                    board.height,
+                    rows.len()
+                ));
+            }
+            for (y, row) in rows.iter().enumerate() {
+                let row: Vec<char> = row.chars().collect();
+                if row.len() > board.width {
+                    return Err(format!(
+                        "pellets: row {} is longer than {} tiles",
+                        y, board.width
+                    ));
+                }
+                for x in 0..board.width {
+                    let c = row.get(x).cloned().unwrap_or(' ');
+                    let h = board.get_tile_of_board_pos(BoardPos { x, y });
+                    if (c == '.' || c == 'o') && !board.tile_is_traversable(h) {
+                        return Err(format!("pellets: ({}, {}) is a wall", x, y));
+                    }
+                    board.has_pellet[h] = c == '.';
+                    board.has_power_pellet[h] = c == 'o';
+                }
+            }
+        }
+        if board.num_pellets_remaining() == 0 {
+            // the level would be over before anything happened
+            return Err("there has to be at least one pellet left".to_string());
+        }
+
+        if let Some((tile, dir)) = self.pacman {
+            game.teleport_pacman(tile)?;
+            game.pacman.dir = dir;
+            game.pacman.wanted_dir = dir;
+        }
+        for kind in GhostKind::ALL.iter() {
+            match self.ghosts[kind.index()] {
+                None => {}
+                Some(GhostSetup::House) => {
+                    let ghost = &mut game.ghosts[kind.index()];
+                    ghost.pos = tile_pos(kind.home());
+                    ghost.state = GhostState::InHouse;
+                    ghost.frightened = false;
+                }
+                Some(GhostSetup::Out {
+                    tile,
+                    dir,
+                    frightened,
+                    eyes,
+                }) => {
+                    game.teleport_ghost(*kind, tile)?;
+                    let ghost = &mut game.ghosts[kind.index()];
+                    ghost.dir = dir;
+                    ghost.frightened = frightened;
+                    if eyes {
+                        ghost.state = GhostState::Eaten;
+                    }
+                }
+            }
+        }
+
+        let mode_index = match (self.mode, self.mode_index) {
+            (_, Some(index)) => index,
+            (Some(GhostMode::Scatter), None) => 0,
+            (Some(GhostMode::Chase), None) => 1,
+            (None, None) => game.mode_index,
+        };
+        // the schedule alternates scatter, chase, scatter, ...
+        let mode = if mode_index % 2 == 0 {
+            GhostMode::Scatter
+        } else {
+            GhostMode::Chase
+        };
+        if self.mode.is_some_and(|m| m != mode) {
+            return Err(format!(
+                "mode_index {} is {:?}, not {:?}",
+                mode_index,
+                mode,
+                self.mode.unwrap()
+            ));
+        }
+        game.mode = mode;
+        game.mode_index = mode_index;
+        if let Some(ticks) = self.mode_ticks {
+            game.mode_ticks = ticks;
+        }
+        if let Some(ticks) = self.fright_ticks {
+            game.fright_ticks = ticks;
+        }
+        if game.fright_ticks == 0 && game.ghosts.iter().any(|g| g.frightened) {
+            return Err("frightened ghosts need fright_ticks".to_string());
+        }
+        if let Some(pellets) = self.house_pellets {
+            game.house_pellets = pellets;
+        }
+        if let Some(ticks) = self.house_idle_ticks {
+            game.house_idle_ticks = ticks;
+        }
+        if !self.ready {
+            game.phase = Phase::Playing;
+        }
+        Ok(())
+    }
+}
+
+// `x,y dir` and whatever follows
+fn parse_actor(value: &str) -> Result<(BoardPos, Dir, Vec<&str>), String> {
+    let words: Vec<&str> = value.split_whitespace().collect();
+    if words.len() < 2 {
+        return Err(format!("expected `x,y direction`, got `{}`", value));
+    }
+    let bad_tile = || format!("bad tile `{}`, expected x,y", words[0]);
+    let (x, y) = words[0].split_once(',').ok_or_else(bad_tile)?;
+    let tile = BoardPos {
+        x: x.trim().parse().map_err(|_| bad_tile())?,
+        y: y.trim().parse().map_err(|_| bad_tile())?,
+    };
+    let dir = match words[1] {
+        "up" => Dir::Up,
+        "left" => Dir::Left,
+        "down" => Dir::Down,
+        "right" => Dir::Right,
+        other => {
+            return Err(format!(
+                "direction must be up, left, down or right, got `{}`",
+                other
+            ))
+        }
+    };
+    Ok((tile, dir, words[2..].to_vec()))
+}
+
+fn parse_ghost(value: &str) -> Result<GhostSetup, String> {
+    if value == "house" {
+        return Ok(GhostSetup::House);
+    }
+    let (tile, dir, rest) = parse_actor(value)?;
+    let (frightened, eyes) = match rest.as_slice() {
+        [] => (false, false),
+        ["frightened"] => (true, false),
+        ["eyes"] => (false, true),
+        _ => {
+            return Err(format!(
+            "expected `house`, or a tile and direction then maybe `frightened` or `eyes`, got `{}`",
+            value
+        ))
+        }
+    };
+    Ok(GhostSetup::Out {
+        tile,
+        dir,
+        frightened,
+        eyes,
+    })
+}
+
+fn parse_number<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} must be a number, got `{}`", key, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Scenario;
+    use crate::board::Board;
+    use crate::config::Config;
+    use crate::game::{Game, GhostKind};
+    use crate::input::GameInput;
+
+    fn apply(scenario: &Scenario) -> Result<Game, String> {
+        let mut game = Game::new(Config::default(), 1, 0);
+        scenario.apply(&mut game).map(|_| game)
+    }
+
+    #[test]
+    fn inky_targeting() {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("scenarios")
+            .join("inky_targeting.txt");
+        let mut game = apply(&Scenario::load(&path).unwrap()).unwrap();
+        game.tick(GameInput::Nil);
+        assert_eq!(game.ghost_target(GhostKind::Inky), Some((7, 20)));
+    }
+
+    #[test]
+    fn pellet_on_a_wall() {
+        let mut rows = Board::new().rows();
+        rows[0].replace_range(0..1, ".");
+        let scenario = Scenario::parse(&format!("pellets:\n{}\n", rows.join("\n"))).unwrap();
+        assert_eq!(apply(&scenario).err().unwrap(), "pellets: (0, 0) is a wall");
+    }
+
+    #[test]
+    fn frightened_without_fright_ticks() {
+        let scenario = Scenario::parse("blinky = 21,26 up frightened").unwrap();
+        assert_eq!(
+            apply(&scenario).err().unwrap(),
+            "frightened ghosts need fright_ticks"
+        );
+        let scenario = Scenario::parse("blinky = 21,26 up frightened\nfright_ticks = 60").unwrap();
+        assert!(apply(&scenario).is_ok());
+    }
+}
@@ -0,0 +1,11 @@
+#version 330 core
+
+layout (location = 0) in vec2 Position;
+layout (location = 1) in vec3 VertexColor;
+
+out vec3 FragColor;
+
+void main() {
+    gl_Position = vec4(Position, 0.f, 1.f);
+    FragColor = VertexColor;
+}
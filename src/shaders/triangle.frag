@@ -1,8 +0,0 @@
-#version 330 core
-
-out vec4 Color;
-
-void main() {
-    Color = vec4(1.0f, 0.5f, 0.2f, 1.0f);
-}
-
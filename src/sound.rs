@@ -1,3 +1,6 @@
+use crate::event::{EventListener, GameEvent};
+use crate::game::Game;
+
 // NOTE: This section was taken fram the sdl2 example for audio-wav.rs
 // TODO: investigate using the mixer feature
 pub struct SoundAsset {
@@ -45,7 +48,73 @@ impl sdl2::audio::AudioCallback for Sound {
             *dst = scaled;
             self.pos += 1;
         }
-        println!("running audio");
+    }
+}
+
+// Plays the sound effects for game events.
+// One sound at a time, a new one cuts off whatever was playing.
+pub struct SoundPlayer {
+    audio: sdl2::AudioSubsystem,
+    begin: SoundAsset,
+    death: SoundAsset,
+    eat_ghost: SoundAsset,
+    extra_life: SoundAsset,
+    playing: Option<sdl2::audio::AudioDevice<Sound>>,
+    pub volume: f32,
+}
+
+impl SoundPlayer {
+    pub fn new(audio: sdl2::AudioSubsystem, sounds_dir: &std::path::Path) -> SoundPlayer {
+        SoundPlayer {
+            audio,
+            begin: SoundAsset::from_wav(&sounds_dir.join("pacman-begin.wav")),
+            death: SoundAsset::from_wav(&sounds_dir.join("pacman-death.wav")),
+            eat_ghost: SoundAsset::from_wav(&sounds_dir.join("pacman-eatghost.wav")),
+            extra_life: SoundAsset::from_wav(&sounds_dir.join("pacman-extrapac.wav")),
+            playing: None,
+            volume: 0.5,
+        }
+    }
+
+    // the tune at the start of a game
+    pub fn play_begin(&mut self) {
+        let data = self.begin.data.clone();
+        self.play(data);
+    }
+
+    fn play(&mut self, data: Vec<u8>) {
+        // the wavs are all 8 bit mono at 11025Hz
+        let spec = sdl2::audio::AudioSpecDesired {
+            freq: Some(11_025),
+            channels: Some(1),
+            samples: None,
+        };
+        let volume = self.volume;
+        // dropping the old device stops it
+        self.playing = None;
+        match self.audio.open_playback(None, &spec, |_| Sound {
+            data,
+            volume,
+            pos: 0,
+        }) {
+            Ok(device) => {
+                device.resume();
+                self.playing = Some(device);
+            }
+            Err(e) => eprintln!("could not play sound: {}", e),
+        }
+    }
+}
+
+impl EventListener for SoundPlayer {
+    fn on_event(&mut self, _game: &Game, event: &GameEvent) {
+        let data = match event {
+            GameEvent::PacmanDied { .. } => self.death.data.clone(),
+            GameEvent::GhostEaten { .. } => self.eat_ghost.data.clone(),
+            GameEvent::ExtraLife => self.extra_life.data.clone(),
+            _ => return,
+        };
+        self.play(data);
     }
 }
 
@@ -0,0 +1,364 @@
+// A local TCP server for watching and poking at a running game.
+//
+// Inspector tools connect on 127.0.0.1 and speak the JSON lines of
+// `protocol.rs`. Every tick each client is sent a "tick" with that tick's
+// events and the state after it. Anything a client sends is a command: the
+// protocol's game commands (state, teleport, set_mode, ...) plus
+//
+//   {"cmd": "pause"}
+//   {"cmd": "resume"}
+//   {"cmd": "step", "ticks": 10}     pause, then run that many ticks
+//
+// which go through the `Debugger`, so they do what the keys do.
+//
+// Nothing here blocks: the front-end calls `poll` once a frame and `send_tick`
+// after every tick. A client that stops reading is dropped rather than
+// holding up the game. Connects and disconnects are left for the front-end to
+// print, see `take_notices`.
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+
+use serde_json::Value;
+
+use crate::debugger::Debugger;
+use crate::game::{Game, TICKS_PER_SECOND};
+use crate::protocol;
+
+// an hour of play, the most one "step" can ask for
+const MAX_STEP_TICKS: u32 = 60 * 60 * TICKS_PER_SECOND;
+// replies a client hasn't read yet, past this it's disconnected
+const MAX_BACKLOG_BYTES: usize = 4 * 1024 * 1024;
+
+pub struct SpectatorServer {
+    listener: TcpListener,
+    clients: Vec<Client>,
+    // connects, disconnects and errors not taken yet
+    notices: Vec<String>,
+}
+
+struct Client {
+    stream: TcpStream,
+    addr: SocketAddr,
+    // bytes received that aren't a whole line yet
+    incoming: Vec<u8>,
+    // bytes not sent yet
+    outgoing: Vec<u8>,
+    closed: bool,
+}
+
+impl SpectatorServer {
+    // Listen on 127.0.0.1:`port`, 0 picks a free port (see `local_addr`)
+    pub fn bind(port: u16) -> Result<SpectatorServer, String> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+            .map_err(|e| format!("could not listen on port {}: {}", port, e))?;
+        listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+        Ok(SpectatorServer {
+            listener,
+            clients: Vec::new(),
+            notices: Vec::new(),
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.listener.local_addr().unwrap()
+    }
+
+    pub fn num_clients(&self) -> usize {
+        self.clients.len()
+    }
+
+    // What happened to the connections since the last call, for the
+    // front-end to show
+    pub fn take_notices(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.notices)
+    }
+
+    // Accept new clients and run the commands that have come in. `game` is
+    // None when no game is being played. Returns true if a command changed
+    // the game in a way inputs can't (teleports, mode changes, loaded
+    // states), which recordings can't follow.
+    pub fn poll(&mut self, mut game: Option<&mut Game>, debugger: &mut Debugger) -> bool {
+        self.accept();
+
+        let mut edited = false;
+        for client in self.clients.iter_mut() {
+            client.receive();
+            while let Some(line) = client.next_line() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let replies = match serde_json::from_str::<Value>(&line) {
+                    Ok(command) => {
+                        let cmd = command.get("cmd").and_then(Value::as_str).unwrap_or("");
+                        let result = handle(&command, game.as_deref_mut(), debugger);
+                        edited |=
+                            result.is_ok() && ["teleport", "set_mode", "load_state"].contains(&cmd);
+                        result
+                    }
+                    Err(e) => Err(format!("bad json: {}", e)),
+                };
+                for reply in replies.unwrap_or_else(|e| vec![protocol::error(&e)]) {
+                    client.queue(&reply);
+                }
+            }
+            client.send();
+        }
+        self.drop_closed();
+        edited
+    }
+
+    // Stream the tick that just happened to every client
+    pub fn send_tick(&mut self, game: &Game) {
+        if self.clients.is_empty() {
+            return;
+        }
+        let tick = protocol::tick_json(game);
+        for client in self.clients.iter_mut() {
+            client.queue(&tick);
+            client.send();
+        }
+        self.drop_closed();
+    }
+
+    fn accept(&mut self) {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, addr)) => {
+                    if stream.set_nonblocking(true).is_err() {
+                        continue;
+                    }
+                    let _ = stream.set_nodelay(true);
+                    self.notices
+                        .push(format!("spectator connected from {}", addr));
+                    self.clients.push(Client {
+                        stream,
+                        addr,
+                        incoming: Vec::new(),
+                        outgoing: Vec::new(),
+                        closed: false,
+                    });
+                }
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    self.notices.push(format!("spectator server: {}", e));
+                    break;
+                }
+            }
+        }
+    }
+
+    fn drop_closed(&mut self) {
+        let notices = &mut self.notices;
+        self.clients.retain(|client| {
+            if client.closed {
+                notices.push(format!("spectator {} disconnected", client.addr));
+            }
+            !client.closed
+        });
+    }
+}
+
+fn handle(
+    command: &Value,
+    game: Option<&mut Game>,
+    debugger: &mut Debugger,
+) -> Result<Vec<Value>, String> {
+    match command.get("cmd").and_then(Value::as_str) {
+        Some("pause") => {
+            if !debugger.paused {
+                debugger.toggle_pause();
+            }
+        }
+        Some("resume") => {
+            if debugger.paused {
+                debugger.toggle_pause();
+            }
+        }
+        Some("step") => {
+            let ticks = match command.get("ticks") {
+                None => 1,
+                Some(n) => n
+                    .as_u64()
+                    .filter(|&n| n <= MAX_STEP_TICKS as u64)
+                    .ok_or_else(|| {
+                        format!("\"ticks\" must be a whole number up to {}", MAX_STEP_TICKS)
+                    })? as u32,
+            };
+            debugger.step(ticks);
+        }
+        Some(cmd) => {
+            let game = game.ok_or("no game is being played")?;
+            return protocol::game_command(game, command)
+                .unwrap_or_else(|| Err(format!("unknown command `{}`", cmd)));
+        }
+        None => return Err("commands need a \"cmd\"".to_string()),
+    }
+    Ok(vec![protocol::ok()])
+}
+
+impl Client {
+    fn receive(&mut self) {
+        let mut buf = [0; 4096];
+        loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) => {
+                    self.closed = true;
+                    break;
+                }
+                Ok(n) => self.incoming.extend_from_slice(&buf[..n]),
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => {
+                    self.closed = true;
+                    break;
+                }
+            }
+        }
+    }
+
+    fn next_line(&mut self) -> Option<String> {
+        let end = self.incoming.iter().position(|&b| b == b'\n')?;
+        let line: Vec<u8> = self.incoming.drain(..=end).collect();
+        Some(String::from_utf8_lossy(&line).into_owned())
+    }
+
+    fn queue(&mut self, reply: &Value) {
+        self.outgoing
+            .extend_from_slice(reply.to_string().as_bytes());
+        self.outgoing.push(b'\n');
+        if self.outgoing.len() > MAX_BACKLOG_BYTES {
+            self.closed = true;
+        }
+    }
+
+    fn send(&mut self) {
+        while !self.outgoing.is_empty() && !self.closed {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => self.closed = true,
+                Ok(n) => {
+                    self.outgoing.drain(..n);
+                }
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => self.closed = true,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{ErrorKind, Read, Write};
+    use std::net::TcpStream;
+    use std::time::Duration;
+
+    use serde_json::Value;
+
+    use super::SpectatorServer;
+    use crate::config::Config;
+    use crate::debugger::Debugger;
+    use crate::game::{Game, GhostKind};
+    use crate::input::GameInput;
+
+    struct TestClient {
+        stream: TcpStream,
+        incoming: Vec<u8>,
+    }
+
+    impl TestClient {
+        // Poll the server until a whole line comes back. Returns it and
+        // whether any poll said the game was edited.
+        fn reply(
+            &mut self,
+            server: &mut SpectatorServer,
+            game: &mut Game,
+            debugger: &mut Debugger,
+        ) -> (Value, bool) {
+            let mut edited = false;
+            for _ in 0..1000 {
+                edited |= server.poll(Some(game), debugger);
+                let mut buf = [0; 4096];
+                match self.stream.read(&mut buf) {
+                    Ok(n) => self.incoming.extend_from_slice(&buf[..n]),
+                    Err(ref e) if e.kind() == ErrorKind::WouldBlock => {}
+                    Err(e) => panic!("{}", e),
+                }
+                if let Some(end) = self.incoming.iter().position(|&b| b == b'\n') {
+                    let line: Vec<u8> = self.incoming.drain(..=end).collect();
+                    return (serde_json::from_slice(&line).unwrap(), edited);
+                }
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            panic!("no reply from the server");
+        }
+    }
+
+    #[test]
+    fn loopback_client() {
+        let mut server = SpectatorServer::bind(0).unwrap();
+        let stream = TcpStream::connect(server.local_addr()).unwrap();
+        stream.set_nonblocking(true).unwrap();
+        let mut client = TestClient {
+            stream,
+            incoming: Vec::new(),
+        };
+        let mut game = Game::new(Config::default(), 1, 7);
+        let mut debugger = Debugger::new();
+
+        for _ in 0..1000 {
+            server.poll(Some(&mut game), &mut debugger);
+            if server.num_clients() == 1 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(server.num_clients(), 1);
+        let notices = server.take_notices();
+        assert_eq!(notices.len(), 1);
+        assert!(notices[0].starts_with("spectator connected from"));
+
+        for _ in 0..3 {
+            game.tick(GameInput::Nil);
+            server.send_tick(&game);
+            let (tick, _) = client.reply(&mut server, &mut game, &mut debugger);
+            assert_eq!(tick["type"], "tick");
+            assert!(tick["events"].is_array());
+            assert_eq!(tick["state"]["tick"], game.ticks);
+        }
+
+        let commands = [
+            (r#"{"cmd": "pause"}"#, false),
+            (r#"{"cmd": "step", "ticks": 2}"#, false),
+            (
+                r#"{"cmd": "teleport", "actor": "inky", "tile": [6, 5]}"#,
+                true,
+            ),
+            (r#"{"cmd": "set_mode", "mode": "chase"}"#, true),
+        ];
+        for &(command, edits) in commands.iter() {
+            writeln!(client.stream, "{}", command).unwrap();
+            let (reply, edited) = client.reply(&mut server, &mut game, &mut debugger);
+            assert_eq!(reply["type"], "ok", "{} got {}", command, reply);
+            assert_eq!(edited, edits, "{}", command);
+        }
+        assert!(debugger.paused);
+        assert_eq!(debugger.steps_pending(), 2);
+
+        writeln!(client.stream, r#"{{"cmd": "step", "ticks": 4294967295}}"#).unwrap();
+        let (reply, _) = client.reply(&mut server, &mut game, &mut debugger);
+        assert_eq!(reply["type"], "error");
+        assert_eq!(debugger.steps_pending(), 2);
+        assert_eq!(game.ghost_tile(GhostKind::Inky).x, 6);
+
+        drop(client);
+        for _ in 0..1000 {
+            server.poll(Some(&mut game), &mut debugger);
+            if server.num_clients() == 0 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(server.num_clients(), 0);
+        assert!(server.take_notices()[0].ends_with("disconnected"));
+    }
+}
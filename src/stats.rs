@@ -0,0 +1,362 @@
+// Gameplay statistics, for studying how players and bots play.
+//
+// A `GameStats` watches one game tick by tick: how long Pac-Man spends on each
+// tile, where Pac-Man died, how many ghosts each energizer got and how long each
+// level took. `export` writes a batch of them out as CSV files plus a heatmap
+// of the tiles over the maze:
+//
+//   games.csv       seed, score, level, ticks, tiles_visited, deaths,
+//                   energizers, ghosts_eaten
+//   deaths.csv      seed, tick, level, x, y, ghost
+//   energizers.csv  seed, tick, level, x, y, ghosts_eaten
+//   levels.csv      seed, level, ticks (a new row each time the level changes)
+//   tiles.csv       x, y, ticks, every open tile, all games together
+//   heatmap.bmp     the same, one 8x8 square per tile
+//
+// Human games get in here by recording them and playing the replay back
+// headless.
+use std::path::Path;
+
+use crate::board::{Board, BoardPos};
+use crate::event::GameEvent;
+use crate::game::{Game, GhostKind, Phase};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameStats {
+    pub seed: u64,
+    // as of the last tick watched
+    pub score: u32,
+    pub level: u32,
+    pub ticks: u64,
+    // ticks Pac-Man spent on each tile while playing, by tile index
+    pub tile_ticks: Vec<u32>,
+    pub deaths: Vec<Death>,
+    pub energizers: Vec<Energizer>,
+    // (level, ticks), in the order played
+    pub levels: Vec<(u32, u64)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Death {
+    pub tick: u64,
+    pub level: u32,
+    pub tile: BoardPos,
+    pub ghost: GhostKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Energizer {
+    pub tick: u64,
+    pub level: u32,
+    pub tile: BoardPos,
+    // before the next energizer or the fright running out
+    pub ghosts_eaten: u32,
+}
+
+impl GameStats {
+    pub fn new(seed: u64, game: &Game) -> GameStats {
+        GameStats {
+            seed,
+            score: 0,
+            level: game.players.current().level,
+            ticks: 0,
+            tile_ticks: vec![0; game.maze.num_tiles],
+            deaths: Vec::new(),
+            energizers: Vec::new(),
+            levels: Vec::new(),
+        }
+    }
+
+    // Call after every tick
+    pub fn observe(&mut self, game: &Game) {
+        let player = game.players.current();
+        self.score = player.score;
+        self.level = player.level;
+        self.ticks += 1;
+        match self.levels.last_mut() {
+            Some((level, ticks)) if *level == player.level => *ticks += 1,
+            _ => self.levels.push((player.level, 1)),
+        }
+
+        let board = game.board();
+        let tile = game.pacman_tile();
+        if game.phase == Phase::Playing && board.contains(tile) {
+            let h = board.get_tile_of_board_pos(tile);
+            if let Some(ticks) = self.tile_ticks.get_mut(h) {
+                *ticks += 1;
+            }
+        }
+
+        for event in game.events() {
+            match event {
+                GameEvent::PacmanDied { ghost } => self.deaths.push(Death {
+                    tick: game.ticks,
+                    level: player.level,
+                    tile,
+                    ghost: *ghost,
+                }),
+                GameEvent::EnergizerEaten { tile } => self.energizers.push(Energizer {
+                    tick: game.ticks,
+                    level: player.level,
+                    tile: *tile,
+                    ghosts_eaten: 0,
+                }),
+                GameEvent::GhostEaten { .. } => {
+                    if let Some(energizer) = self.energizers.last_mut() {
+                        energizer.ghosts_eaten += 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn tiles_visited(&self) -> usize {
+        self.tile_ticks.iter().filter(|&&t| t > 0).count()
+    }
+
+    pub fn ghosts_eaten(&self) -> u32 {
+        self.energizers.iter().map(|e| e.ghosts_eaten).sum()
+    }
+}
+
+// Write the CSV files and heatmap for `games`, all played on `maze`, into
+// `dir`, creating it if needed
+pub fn export(dir: &Path, maze: &Board, games: &[&GameStats]) -> Result<(), String> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| format!("could not create {}: {}", dir.display(), e))?;
+
+    let mut rows =
+        vec!["seed,score,level,ticks,tiles_visited,deaths,energizers,ghosts_eaten".to_string()];
+    for g in games {
+        rows.push(format!(
+            "{},{},{},{},{},{},{},{}",
+            g.seed,
+            g.score,
+            g.level,
+            g.ticks,
+            g.tiles_visited(),
+            g.deaths.len(),
+            g.energizers.len(),
+            g.ghosts_eaten()
+        ));
+    }
+    write_csv(&dir.join("games.csv"), &rows)?;
+
+    let mut rows = vec!["seed,tick,level,x,y,ghost".to_string()];
+    for g in games {
+        for d in g.deaths.iter() {
+            rows.push(format!(
+                "{},{},{},{},{},{}",
+                g.seed,
+                d.tick,
+                d.level,
+                d.tile.x,
+                d.tile.y,
+                format!("{:?}", d.ghost).to_lowercase()
+            ));
+        }
+    }
+    write_csv(&dir.join("deaths.csv"), &rows)?;
+
+    let mut rows = vec!["seed,tick,level,x,y,ghosts_eaten".to_string()];
+    for g in games {
+        for e in g.energizers.iter() {
+            rows.push(format!(
+                "{},{},{},{},{},{}",
+                g.seed, e.tick, e.level, e.tile.x, e.tile.y, e.ghosts_eaten
+            ));
+        }
+    }
+    write_csv(&dir.join("energizers.csv"), &rows)?;
+
+    let mut rows = vec!["seed,level,ticks".to_string()];
+    for g in games {
+        for (level, ticks) in g.levels.iter() {
+            rows.push(format!("{},{},{}", g.seed, level, ticks));
+        }
+    }
+    write_csv(&dir.join("levels.csv"), &rows)?;
+
+    let mut tile_ticks = vec![0u64; maze.num_tiles];
+    for g in games {
+        for (total, &ticks) in tile_ticks.iter_mut().zip(g.tile_ticks.iter()) {
+            *total += ticks as u64;
+        }
+    }
+    let mut rows = vec!["x,y,ticks".to_string()];
+    for (h, ticks) in tile_ticks.iter().enumerate() {
+        if maze.tile_is_traversable(h) {
+            let pos = maze.get_board_pos_of_tile(h);
+            rows.push(format!("{},{},{}", pos.x, pos.y, ticks));
+        }
+    }
+    write_csv(&dir.join("tiles.csv"), &rows)?;
+
+    write_heatmap(&dir.join("heatmap.bmp"), maze, &tile_ticks)
+}
+
+fn write_csv(path: &Path, rows: &[String]) -> Result<(), String> {
+    let mut text = rows.join("\n");
+    text.push('\n');
+    std::fs::write(path, text).map_err(|e| format!("could not write {}: {}", path.display(), e))
+}
+
+// `ticks` per tile as colors over the maze: walls dark blue, open tiles from
+// black through red and yellow to white for the most visited one
+pub fn write_heatmap(path: &Path, maze: &Board, ticks: &[u64]) -> Result<(), String> {
+    let tile = Board::TILE_WIDTH as usize;
+    let (width, height) = (maze.width * tile, maze.height * tile);
+    let most = ticks.iter().cloned().max().unwrap_or(0).max(1);
+
+    let mut pixels = vec![[0u8; 3]; width * height];
+    for (h, &ticks) in ticks.iter().enumerate().take(maze.num_tiles) {
+        let color = if maze.tile_is_ghost_door(h) {
+            [255, 184, 222]
+        } else if !maze.tile_is_traversable(h) {
+            [0, 0, 96]
+        } else {
+            heat_color(ticks as f64 / most as f64)
+        };
+        let pos = maze.get_board_pos_of_tile(h);
+        for y in pos.y * tile..(pos.y + 1) * tile {
+            for x in pos.x * tile..(pos.x + 1) * tile {
+                pixels[y * width + x] = color;
+            }
+        }
+    }
+    write_bmp(path, width, height, &pixels)
+}
+
+// 0 black, then red, yellow and 1 white
+fn heat_color(heat: f64) -> [u8; 3] {
+    let channel = |from: f64| ((heat * 3.0 - from).clamp(0.0, 1.0) * 255.0) as u8;
+    [channel(0.0), channel(1.0), channel(2.0)]
+}
+
+// An uncompressed 24 bit BMP of `pixels`, RGB rows from the top
+pub fn write_bmp(
+    path: &Path,
+    width: usize,
+    height: usize,
+    pixels: &[[u8; 3]],
+) -> Result<(), String> {
+    assert_eq!(pixels.len(), width * height);
+    // rows are padded to 4 bytes
+    let row_bytes = (width * 3).div_ceil(4) * 4;
+    let image_bytes = row_bytes * height;
+    let header_bytes = 14 + 40;
+
+    let mut out = Vec::with_capacity(header_bytes + image_bytes);
+    // file header
+    out.extend_from_slice(b"BM");
+    out.extend_from_slice(&((header_bytes + image_bytes) as u32).to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&(header_bytes as u32).to_le_bytes());
+    // BITMAPINFOHEADER
+    out.extend_from_slice(&40u32.to_le_bytes());
+    out.extend_from_slice(&(width as i32).to_le_bytes());
+    out.extend_from_slice(&(height as i32).to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&24u16.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&(image_bytes as u32).to_le_bytes());
+    // 72 dpi
+    out.extend_from_slice(&2835i32.to_le_bytes());
+    out.extend_from_slice(&2835i32.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    // bottom row first, BGR
+    for row in pixels.chunks(width).rev() {
+        for [r, g, b] in row.iter() {
+            out.extend_from_slice(&[*b, *g, *r]);
+        }
+        out.resize(out.len() + row_bytes - width * 3, 0);
+    }
+
+    std::fs::write(path, out).map_err(|e| format!("could not write {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{export, Death, Energizer, GameStats};
+    use crate::board::{Board, BoardPos};
+    use crate::game::GhostKind;
+
+    fn game(seed: u64, maze: &Board, visited: &[(BoardPos, u32)]) -> GameStats {
+        let mut tile_ticks = vec![0; maze.num_tiles];
+        for &(pos, ticks) in visited {
+            tile_ticks[maze.get_tile_of_board_pos(pos)] = ticks;
+        }
+        GameStats {
+            seed,
+            score: 1230,
+            level: 2,
+            ticks: 5000,
+            tile_ticks,
+            deaths: vec![Death {
+                tick: 4000,
+                level: 2,
+                tile: BoardPos { x: 6, y: 5 },
+                ghost: GhostKind::Pinky,
+            }],
+            energizers: vec![Energizer {
+                tick: 3000,
+                level: 2,
+                tile: BoardPos { x: 1, y: 3 },
+                ghosts_eaten: 3,
+            }],
+            levels: vec![(1, 2500), (2, 2500)],
+        }
+    }
+
+    #[test]
+    fn exports_csv_and_heatmap() {
+        let maze = Board::new();
+        let (busy, quiet) = (BoardPos { x: 1, y: 5 }, BoardPos { x: 2, y: 5 });
+        let a = game(1, &maze, &[(busy, 30), (quiet, 10)]);
+        let b = game(2, &maze, &[(busy, 30)]);
+        let dir = std::env::temp_dir().join(format!("pacman-stats-{}", std::process::id()));
+        export(&dir, &maze, &[&a, &b]).unwrap();
+        let read = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap();
+
+        assert_eq!(
+            read("games.csv"),
+            "seed,score,level,ticks,tiles_visited,deaths,energizers,ghosts_eaten\n\
+             1,1230,2,5000,2,1,1,3\n\
+             2,1230,2,5000,1,1,1,3\n"
+        );
+        assert_eq!(
+            read("deaths.csv"),
+            "seed,tick,level,x,y,ghost\n1,4000,2,6,5,pinky\n2,4000,2,6,5,pinky\n"
+        );
+        assert_eq!(
+            read("levels.csv"),
+            "seed,level,ticks\n1,1,2500\n1,2,2500\n2,1,2500\n2,2,2500\n"
+        );
+        let tiles = read("tiles.csv");
+        let open = (0..maze.num_tiles)
+            .filter(|&h| maze.tile_is_traversable(h))
+            .count();
+        assert_eq!(tiles.lines().count(), 1 + open);
+        assert!(tiles.lines().any(|row| row == "1,5,60"));
+        assert!(tiles.lines().any(|row| row == "2,5,10"));
+
+        // 24 bit, bottom row first, BGR
+        let bmp = std::fs::read(dir.join("heatmap.bmp")).unwrap();
+        let tile = Board::TILE_WIDTH as usize;
+        let (width, height) = (maze.width * tile, maze.height * tile);
+        assert_eq!(&bmp[..2], b"BM");
+        assert_eq!(bmp.len(), 54 + width * 3 * height);
+        let pixel = |pos: BoardPos| {
+            let (x, y) = (pos.x * tile, height - 1 - pos.y * tile);
+            let i = 54 + (y * width + x) * 3;
+            [bmp[i + 2], bmp[i + 1], bmp[i]]
+        };
+        assert_eq!(pixel(busy), [255, 255, 255]);
+        assert_eq!(pixel(quiet), [127, 0, 0]);
+        assert_eq!(pixel(BoardPos { x: 0, y: 5 }), [0, 0, 96]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,348 @@
+// Tile traces: where every actor is on every tick, for checking the
+// simulation against the arcade.
+//
+// A trace is plain text, one tick per line, so an emulator script can log one
+// from the real ROMs:
+//
+//   # tick mode pacman blinky pinky inky clyde
+//   812 chase 14,23 13,11 normal 13,14 house 11,14 house 15,14 house
+//
+// Tiles are (x, y) in maze tiles, 0,0 top left as in maze files; turning the
+// arcade's rotated screen into that is the logger's job. Ghosts are a tile and
+// one of `house`, `normal`, `frightened` or `eyes`, the mode is `scatter` or
+// `chase`. Anything a logger can't tell is written `-` and never compared.
+// Tick 1 is the first frame after the game starts, READY! included, and each
+// line is the state after that tick. Lines starting with # are comments.
+//
+// `compare` goes through a reference and finds the first tick ours differs
+// on. References may skip ticks; those are just not compared.
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::board::BoardPos;
+use crate::game::{Game, GhostKind, GhostMode, GhostState};
+
+// x, y
+pub type Tile = (i32, i32);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GhostTrace {
+    House,
+    Normal,
+    Frightened,
+    Eyes,
+}
+
+// One line of a trace, None for `-`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TickTrace {
+    pub tick: u64,
+    pub mode: Option<GhostMode>,
+    pub pacman: Option<Tile>,
+    // indexed by `GhostKind::index`
+    pub ghosts: [(Option<Tile>, Option<GhostTrace>); 4],
+}
+
+// Where our trace and the reference first disagree
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mismatch {
+    pub tick: u64,
+    // "what: ours vs reference", one per field that differs
+    pub diff: Vec<String>,
+}
+
+impl TickTrace {
+    pub fn of(game: &Game) -> TickTrace {
+        let tile = |pos: BoardPos| Some((pos.x as i32, pos.y as i32));
+        let ghost = |kind: GhostKind| {
+            let ghost = &game.ghosts[kind.index()];
+            let state = match ghost.state {
+                GhostState::InHouse | GhostState::LeavingHouse | GhostState::EnteringHouse => {
+                    GhostTrace::House
+                }
+                GhostState::Eaten => GhostTrace::Eyes,
+                GhostState::Active if ghost.frightened => GhostTrace::Frightened,
+                GhostState::Active => GhostTrace::Normal,
+            };
+            (tile(game.ghost_tile(kind)), Some(state))
+        };
+        TickTrace {
+            tick: game.ticks,
+            mode: Some(game.mode),
+            pacman: tile(game.pacman_tile()),
+            ghosts: [
+                ghost(GhostKind::Blinky),
+                ghost(GhostKind::Pinky),
+                ghost(GhostKind::Inky),
+                ghost(GhostKind::Clyde),
+            ],
+        }
+    }
+
+    // One line of a trace, without the newline. None for comments and blank
+    // lines.
+    pub fn parse(line: &str) -> Result<Option<TickTrace>, String> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(None);
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() != 11 {
+            return Err(format!("expected 11 fields, got {}", fields.len()));
+        }
+        let tick = fields[0]
+            .parse()
+            .map_err(|_| format!("bad tick `{}`", fields[0]))?;
+        let mode = unknown_or(fields[1], |s| match s {
+            "scatter" => Ok(GhostMode::Scatter),
+            "chase" => Ok(GhostMode::Chase),
+            _ => Err(format!("bad mode `{}`", s)),
+        })?;
+        let mut ghosts = [(None, None); 4];
+        for (i, ghost) in ghosts.iter_mut().enumerate() {
+            let tile = unknown_or(fields[3 + 2 * i], parse_tile)?;
+            let state = unknown_or(fields[4 + 2 * i], |s| match s {
+                "house" => Ok(GhostTrace::House),
+                "normal" => Ok(GhostTrace::Normal),
+                "frightened" => Ok(GhostTrace::Frightened),
+                "eyes" => Ok(GhostTrace::Eyes),
+                _ => Err(format!("bad ghost state `{}`", s)),
+            })?;
+            *ghost = (tile, state);
+        }
+        Ok(Some(TickTrace {
+            tick,
+            mode,
+            pacman: unknown_or(fields[2], parse_tile)?,
+            ghosts,
+        }))
+    }
+
+    // What differs from `reference`, skipping what it doesn't know
+    pub fn diff(&self, reference: &TickTrace) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut field = |name: &str, ours: String, theirs: Option<String>| {
+            if let Some(theirs) = theirs {
+                if ours != theirs {
+                    lines.push(format!("{}: {} vs {}", name, ours, theirs));
+                }
+            }
+        };
+        field(
+            "mode",
+            show(self.mode.map(mode_name)),
+            reference.mode.map(mode_name).map(str::to_string),
+        );
+        field(
+            "pacman",
+            show(self.pacman.map(tile_name)),
+            reference.pacman.map(tile_name),
+        );
+        for kind in GhostKind::ALL.iter() {
+            let name = format!("{:?}", kind).to_lowercase();
+            let (ours, theirs) = (self.ghosts[kind.index()], reference.ghosts[kind.index()]);
+            field(&name, show(ours.0.map(tile_name)), theirs.0.map(tile_name));
+            field(
+                &format!("{} state", name),
+                show(ours.1.map(state_name)),
+                theirs.1.map(state_name).map(str::to_string),
+            );
+        }
+        lines
+    }
+}
+
+impl fmt::Display for TickTrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            self.tick,
+            show(self.mode.map(mode_name)),
+            show(self.pacman.map(tile_name))
+        )?;
+        for (tile, state) in self.ghosts.iter() {
+            write!(
+                f,
+                " {} {}",
+                show(tile.map(tile_name)),
+                show(state.map(state_name))
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "first mismatch at tick {}, ours vs reference:",
+            self.tick
+        )?;
+        for line in self.diff.iter() {
+            writeln!(f, "  {}", line)?;
+        }
+        Ok(())
+    }
+}
+
+// `x,y`
+fn parse_tile(s: &str) -> Result<Tile, String> {
+    let bad = || format!("bad tile `{}`, expected x,y", s);
+    let (x, y) = s.split_once(',').ok_or_else(bad)?;
+    Ok((x.parse().map_err(|_| bad())?, y.parse().map_err(|_| bad())?))
+}
+
+fn unknown_or<T>(s: &str, parse: impl Fn(&str) -> Result<T, String>) -> Result<Option<T>, String> {
+    if s == "-" {
+        Ok(None)
+    } else {
+        parse(s).map(Some)
+    }
+}
+
+fn tile_name((x, y): Tile) -> String {
+    format!("{},{}", x, y)
+}
+
+fn mode_name(mode: GhostMode) -> &'static str {
+    match mode {
+        GhostMode::Scatter => "scatter",
+        GhostMode::Chase => "chase",
+    }
+}
+
+fn state_name(state: GhostTrace) -> &'static str {
+    match state {
+        GhostTrace::House => "house",
+        GhostTrace::Normal => "normal",
+        GhostTrace::Frightened => "frightened",
+        GhostTrace::Eyes => "eyes",
+    }
+}
+
+fn show<T: fmt::Display>(value: Option<T>) -> String {
+    value.map_or_else(|| "-".to_string(), |v| v.to_string())
+}
+
+pub fn load(path: &Path) -> Result<Vec<TickTrace>, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+    let mut ticks = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let trace =
+            TickTrace::parse(line).map_err(|e| format!("{}:{}: {}", path.display(), i + 1, e))?;
+        if let Some(trace) = trace {
+            if ticks
+                .last()
+                .is_some_and(|t: &TickTrace| t.tick >= trace.tick)
+            {
+                return Err(format!("{}:{}: ticks have to go up", path.display(), i + 1));
+            }
+            ticks.push(trace);
+        }
+    }
+    Ok(ticks)
+}
+
+// The first tick of `reference` that `ours` doesn't match, or doesn't reach
+pub fn compare(ours: &[TickTrace], reference: &[TickTrace]) -> Option<Mismatch> {
+    let mut ours = ours.iter().peekable();
+    for theirs in reference {
+        while ours.next_if(|t| t.tick < theirs.tick).is_some() {}
+        let diff = match ours.peek() {
+            Some(t) if t.tick == theirs.tick => t.diff(theirs),
+            Some(_) => vec!["our trace skips this tick".to_string()],
+            None => vec!["our trace ends before this tick".to_string()],
+        };
+        if !diff.is_empty() {
+            return Some(Mismatch {
+                tick: theirs.tick,
+                diff,
+            });
+        }
+    }
+    None
+}
+
+pub struct TileTraceWriter {
+    out: BufWriter<File>,
+}
+
+impl TileTraceWriter {
+    pub fn create(path: &Path) -> Result<TileTraceWriter, String> {
+        let file = File::create(path)
+            .map_err(|e| format!("could not create {}: {}", path.display(), e))?;
+        let mut out = BufWriter::new(file);
+        writeln!(out, "# tick mode pacman blinky pinky inky clyde")
+            .map_err(|e| format!("could not write trace: {}", e))?;
+        Ok(TileTraceWriter { out })
+    }
+
+    pub fn write_tick(&mut self, game: &Game) -> Result<(), String> {
+        writeln!(self.out, "{}", TickTrace::of(game))
+            .map_err(|e| format!("could not write trace: {}", e))
+    }
+
+    pub fn finish(mut self) -> Result<(), String> {
+        self.out
+            .flush()
+            .map_err(|e| format!("could not write trace: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compare, TickTrace};
+    use crate::bot::Autopilot;
+    use crate::config::Config;
+    use crate::game::Game;
+
+    fn ours(ticks: usize) -> Vec<TickTrace> {
+        let autopilot = Autopilot::new();
+        let mut game = Game::new(Config::default(), 1, 9);
+        let mut trace = Vec::new();
+        for _ in 0..ticks {
+            game.tick(autopilot.choose_input(&game));
+            trace.push(TickTrace::of(&game));
+        }
+        trace
+    }
+
+    #[test]
+    fn finds_the_first_divergence() {
+        let ours = ours(300);
+        // a reference logged every other tick, through the text format, that
+        // couldn't see blinky's state
+        let mut reference: Vec<TickTrace> = ours
+            .iter()
+            .filter(|t| t.tick % 2 == 0)
+            .map(|t| TickTrace::parse(&t.to_string()).unwrap().unwrap())
+            .collect();
+        for t in reference.iter_mut() {
+            t.ghosts[0].1 = None;
+        }
+        assert_eq!(compare(&ours, &reference), None);
+
+        // pac-man one tile off from tick 200, and blinky's state wrong from
+        // tick 100, which isn't compared
+        let (x, y) = ours[199].pacman.unwrap();
+        for t in reference.iter_mut().filter(|t| t.tick >= 200) {
+            t.pacman = Some((x + 1, y));
+            t.ghosts[0].1 = ours[0].ghosts[0].1;
+        }
+        let mismatch = compare(&ours, &reference).unwrap();
+        assert_eq!(mismatch.tick, 200);
+        assert!(mismatch
+            .diff
+            .contains(&format!("pacman: {},{} vs {},{}", x, y, x + 1, y)));
+
+        // a reference that goes on longer than we played
+        let mismatch = compare(&ours[..150], &reference[..100]).unwrap();
+        assert_eq!(mismatch.tick, 152);
+        assert_eq!(mismatch.diff, ["our trace ends before this tick"]);
+    }
+}
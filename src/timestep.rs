@@ -0,0 +1,112 @@
+// Fixed timestep for the simulation, independent of the render rate.
+//
+// Each render frame asks `advance` how many ticks are due. Time carries over
+// between frames in an accumulator so the game runs at the arcade's rate on
+// average whatever the frame rate is. If we fall too far behind (debugger,
+// window drag, slow machine) the extra ticks are dropped instead of trying to
+// catch up all at once.
+use std::time::{Duration, Instant};
+
+// The arcade's video refresh; the game logic runs once per frame
+pub const ARCADE_TICK_HZ: f64 = 60.606_060;
+
+// how many ticks one frame may run before we give up and drop the rest
+const DEFAULT_MAX_TICKS_PER_FRAME: u32 = 8;
+
+// how much of a wait we hand to the OS scheduler, the rest we spin
+const SPIN_MARGIN: Duration = Duration::from_millis(1);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TickReport {
+    // ticks to run this frame
+    pub ticks: u32,
+    // ticks that were due but skipped because we were too far behind
+    pub dropped: u32,
+}
+
+impl TickReport {
+    pub fn is_overrun(&self) -> bool {
+        self.dropped > 0
+    }
+}
+
+pub struct FixedTimestep {
+    pub tick_duration: Duration,
+    pub max_ticks_per_frame: u32,
+    accumulator: Duration,
+    last_advance: Option<Instant>,
+    // totals since creation
+    pub total_ticks: u64,
+    pub total_dropped: u64,
+}
+
+impl FixedTimestep {
+    pub fn new(hz: f64) -> FixedTimestep {
+        FixedTimestep {
+            tick_duration: Duration::from_secs_f64(1.0 / hz),
+            max_ticks_per_frame: DEFAULT_MAX_TICKS_PER_FRAME,
+            accumulator: Duration::from_secs(0),
+            last_advance: None,
+            total_ticks: 0,
+            total_dropped: 0,
+        }
+    }
+
+    pub fn arcade() -> FixedTimestep {
+        FixedTimestep::new(ARCADE_TICK_HZ)
+    }
+
+    pub fn advance(&mut self, now: Instant) -> TickReport {
+        let elapsed = match self.last_advance {
+            Some(last) => now.saturating_duration_since(last),
+            // the first frame runs one tick so there's something to draw
+            None => self.tick_duration,
+        };
+        self.last_advance = Some(now);
+        self.accumulator += elapsed;
+
+        let mut ticks = 0;
+        while self.accumulator >= self.tick_duration && ticks < self.max_ticks_per_frame {
+            self.accumulator -= self.tick_duration;
+            ticks += 1;
+        }
+
+        let mut dropped = 0;
+        while self.accumulator >= self.tick_duration {
+            self.accumulator -= self.tick_duration;
+            dropped += 1;
+        }
+
+        self.total_ticks += ticks as u64;
+        self.total_dropped += dropped as u64;
+        TickReport { ticks, dropped }
+    }
+
+    // When the next tick will be due
+    pub fn next_tick_at(&self) -> Instant {
+        let last = self.last_advance.unwrap_or_else(Instant::now);
+        last + (self.tick_duration - self.accumulator)
+    }
+
+    // How far we are between the last tick and the next one, [0, 1)
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator.as_secs_f64() / self.tick_duration.as_secs_f64()) as f32
+    }
+}
+
+// Sleep most of the way to `deadline` and spin the last little bit.
+// `std::thread::sleep` on its own can overshoot by a few ms, spinning the whole
+// way burns a core.
+pub fn sleep_until(deadline: Instant) {
+    let now = Instant::now();
+    if deadline <= now {
+        return;
+    }
+    let remaining = deadline - now;
+    if remaining > SPIN_MARGIN {
+        std::thread::sleep(remaining - SPIN_MARGIN);
+    }
+    while Instant::now() < deadline {
+        std::thread::yield_now();
+    }
+}
@@ -0,0 +1,139 @@
+// Decision traces: every choice a ghost makes at an intersection and every
+// turn Pac-Man takes, as JSON lines, for analysing the AI offline the way the
+// Dossier does.
+//
+// The game only keeps decisions while `Game::trace_decisions` is on, and like
+// events they only cover the last tick. A ghost line looks like
+//
+//   {"tick": 812, "actor": "pinky", "tile": [6, 5], "heading": "up",
+//    "mode": "chase", "target": [1, 9], "chosen": "left",
+//    "options": [{"dir": "left", "distance_sq": 17}, {"dir": "right", ...}]}
+//
+// where `distance_sq` is from the tile each option leads to, the number the
+// arcade compares. Frightened ghosts have no target and no distances. Pac-Man
+// lines are {"tick", "actor": "pacman", "tile", "from", "to"}.
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::board::{Board, BoardPos, Dir};
+use crate::game::{Game, GhostKind, GhostMode};
+use crate::protocol::dir_name;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceMode {
+    Scatter,
+    Chase,
+    Frightened,
+    // eaten, heading home
+    Eyes,
+}
+
+impl TraceMode {
+    pub fn from_mode(mode: GhostMode) -> TraceMode {
+        match mode {
+            GhostMode::Scatter => TraceMode::Scatter,
+            GhostMode::Chase => TraceMode::Chase,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GhostDecision {
+    pub ghost: GhostKind,
+    pub tile: BoardPos,
+    // the way it was going into the tile
+    pub heading: Dir,
+    pub mode: TraceMode,
+    pub target: Option<(i32, i32)>,
+    // every way it could go, with the squared distance to the target
+    pub options: Vec<(Dir, Option<i32>)>,
+    pub chosen: Dir,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Decision {
+    Ghost(GhostDecision),
+    PacmanTurn { tile: BoardPos, from: Dir, to: Dir },
+}
+
+// `options` from `tile` paired with their squared distances to `target`, if
+// there is one
+pub fn with_distances(
+    board: &Board,
+    tile: BoardPos,
+    options: &[Dir],
+    target: Option<(i32, i32)>,
+) -> Vec<(Dir, Option<i32>)> {
+    options
+        .iter()
+        .map(|&d| {
+            let distance = target.and_then(|(x, y)| {
+                let next = board.neighbor(tile, d)?;
+                let (dx, dy) = (next.x as i32 - x, next.y as i32 - y);
+                Some(dx * dx + dy * dy)
+            });
+            (d, distance)
+        })
+        .collect()
+}
+
+pub fn decision_json(tick: u64, decision: &Decision) -> Value {
+    match decision {
+        Decision::Ghost(d) => {
+            let options: Vec<Value> = d
+                .options
+                .iter()
+                .map(|&(dir, distance)| json!({"dir": dir_name(dir), "distance_sq": distance}))
+                .collect();
+            json!({
+                "tick": tick,
+                "actor": format!("{:?}", d.ghost).to_lowercase(),
+                "tile": [d.tile.x, d.tile.y],
+                "heading": dir_name(d.heading),
+                "mode": format!("{:?}", d.mode).to_lowercase(),
+                "target": d.target.map(|(x, y)| json!([x, y])),
+                "options": options,
+                "chosen": dir_name(d.chosen),
+            })
+        }
+        Decision::PacmanTurn { tile, from, to } => json!({
+            "tick": tick,
+            "actor": "pacman",
+            "tile": [tile.x, tile.y],
+            "from": dir_name(*from),
+            "to": dir_name(*to),
+        }),
+    }
+}
+
+pub struct TraceWriter {
+    out: BufWriter<File>,
+}
+
+impl TraceWriter {
+    pub fn create(path: &Path) -> Result<TraceWriter, String> {
+        let file = File::create(path)
+            .map_err(|e| format!("could not create {}: {}", path.display(), e))?;
+        Ok(TraceWriter {
+            out: BufWriter::new(file),
+        })
+    }
+
+    // The decisions made in the tick `game` just played
+    pub fn write_tick(&mut self, game: &Game) -> Result<(), String> {
+        for decision in game.decisions() {
+            writeln!(self.out, "{}", decision_json(game.ticks, decision))
+                .map_err(|e| format!("could not write trace: {}", e))?;
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<(), String> {
+        self.out
+            .flush()
+            .map_err(|e| format!("could not write trace: {}", e))
+    }
+}
@@ -1 +1,191 @@
-pub type Vec2 = cgmath::Vector2<f32>;
+// Fixed point positions and distances, so the simulation is all integer math
+// and plays out the same on every platform and compiler.
+//
+// A `Fixed` is a number of pixels with 8 bits of sub-pixel. That's finer than
+// the game needs: every speed in the Dossier's tables is a multiple of 1/16
+// pixel per tick. Going to whole pixels or tiles floors, like the arcade's
+// pixel counters, so there's no rounding mode to get wrong.
+use std::fmt;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+// sub-pixel steps per pixel
+pub const SUBPIXELS: i32 = 256;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Fixed(i32);
+
+impl Fixed {
+    pub const ZERO: Fixed = Fixed(0);
+
+    pub const fn from_raw(raw: i32) -> Fixed {
+        Fixed(raw)
+    }
+
+    // in sub-pixels, for saving
+    pub const fn raw(self) -> i32 {
+        self.0
+    }
+
+    pub const fn pixels(px: i32) -> Fixed {
+        Fixed(px * SUBPIXELS)
+    }
+
+    // `pct` percent of this, e.g. a speed from the Dossier's tables. Rounds
+    // toward zero, though every table entry comes out exact.
+    pub const fn percent(self, pct: u32) -> Fixed {
+        Fixed(self.0 * pct as i32 / 100)
+    }
+
+    // The whole pixel this is in, rounding down
+    pub fn floor_pixels(self) -> i32 {
+        self.0.div_euclid(SUBPIXELS)
+    }
+
+    // How many whole `unit`s fit, rounding down
+    pub fn div_euclid(self, unit: Fixed) -> i32 {
+        self.0.div_euclid(unit.0)
+    }
+
+    pub fn rem_euclid(self, unit: Fixed) -> Fixed {
+        Fixed(self.0.rem_euclid(unit.0))
+    }
+
+    pub fn abs(self) -> Fixed {
+        Fixed(self.0.abs())
+    }
+
+    // -1, 0 or 1
+    pub fn signum(self) -> i32 {
+        self.0.signum()
+    }
+
+    // only for drawing and for showing to people, never for the simulation
+    pub fn to_f32(self) -> f32 {
+        self.0 as f32 / SUBPIXELS as f32
+    }
+}
+
+// in pixels, which f32 shows exactly
+impl fmt::Debug for Fixed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.to_f32())
+    }
+}
+
+impl Add for Fixed {
+    type Output = Fixed;
+    fn add(self, other: Fixed) -> Fixed {
+        Fixed(self.0 + other.0)
+    }
+}
+
+impl Sub for Fixed {
+    type Output = Fixed;
+    fn sub(self, other: Fixed) -> Fixed {
+        Fixed(self.0 - other.0)
+    }
+}
+
+impl Neg for Fixed {
+    type Output = Fixed;
+    fn neg(self) -> Fixed {
+        Fixed(-self.0)
+    }
+}
+
+impl Mul<i32> for Fixed {
+    type Output = Fixed;
+    fn mul(self, n: i32) -> Fixed {
+        Fixed(self.0 * n)
+    }
+}
+
+impl AddAssign for Fixed {
+    fn add_assign(&mut self, other: Fixed) {
+        self.0 += other.0;
+    }
+}
+
+impl SubAssign for Fixed {
+    fn sub_assign(&mut self, other: Fixed) {
+        self.0 -= other.0;
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub struct Vec2 {
+    pub x: Fixed,
+    pub y: Fixed,
+}
+
+impl Vec2 {
+    pub const fn new(x: Fixed, y: Fixed) -> Vec2 {
+        Vec2 { x, y }
+    }
+
+    pub const fn from_pixels(x: i32, y: i32) -> Vec2 {
+        Vec2 {
+            x: Fixed::pixels(x),
+            y: Fixed::pixels(y),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Fixed, Vec2};
+    use crate::board::{Board, BoardPos};
+    use crate::game::{tile_of, tile_pos};
+
+    const TILE: Fixed = Fixed::pixels(Board::TILE_WIDTH);
+
+    #[test]
+    fn rounds_down_on_tile_boundaries() {
+        let last_subpixel = Fixed::from_raw(1);
+        for tile in -3..3 {
+            let start = TILE * tile;
+            assert_eq!(start.div_euclid(TILE), tile);
+            assert_eq!(start.rem_euclid(TILE), Fixed::ZERO);
+            assert_eq!((start - last_subpixel).div_euclid(TILE), tile - 1);
+            assert_eq!(
+                (start - last_subpixel).rem_euclid(TILE),
+                TILE - last_subpixel
+            );
+            assert_eq!(start.floor_pixels(), tile * Board::TILE_WIDTH);
+        }
+        // negative positions floor away from zero, not toward it
+        assert_eq!(Fixed::from_raw(-1).floor_pixels(), -1);
+        assert_eq!(Fixed::from_raw(-1).div_euclid(TILE), -1);
+        assert_eq!(Fixed::pixels(-3).percent(50), Fixed::from_raw(-384));
+    }
+
+    #[test]
+    fn converts_to_tiles() {
+        let board = Board::new();
+        let pos = BoardPos { x: 13, y: 23 };
+        assert_eq!(BoardPos::from(tile_pos(pos)), pos);
+        assert_eq!(tile_of(&board, tile_pos(pos)), pos);
+
+        // an actor's tile changes when its center crosses the boundary,
+        // half a tile before its top-left corner does
+        let half = Fixed::pixels(Board::TILE_WIDTH / 2);
+        let just_before = Vec2::new(tile_pos(pos).x + half - Fixed::from_raw(1), tile_pos(pos).y);
+        assert_eq!(tile_of(&board, just_before), pos);
+        let on_it = Vec2::new(tile_pos(pos).x + half, tile_pos(pos).y);
+        assert_eq!(tile_of(&board, on_it), BoardPos { x: 14, y: 23 });
+
+        // left of the maze is the tunnel's far end, and corners stay on the board
+        let in_the_tunnel = Vec2::new(-TILE, tile_pos(pos).y);
+        assert_eq!(
+            tile_of(&board, in_the_tunnel),
+            BoardPos {
+                x: board.width - 1,
+                y: 23
+            }
+        );
+        assert_eq!(
+            BoardPos::from(Vec2::new(-half, -half)),
+            BoardPos { x: 0, y: 0 }
+        );
+    }
+}